@@ -0,0 +1,48 @@
+//! How long a tick of the clock takes with thousands of speeches queued in the
+//!  brevity mode, where it moves the current speaker's speeches, against the
+//!  full re-sort it used to do every second, e.g.
+//!
+//!     cargo bench --no-default-features --bench resort
+
+use std::sync::{Arc, Mutex};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use speakeasy::discussion::{Discussion, DiscussionSettings, PriorityMode};
+
+fn queued_discussion(n_speakers: usize, n_speeches: usize) -> Arc<Mutex<Discussion>> {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    {
+        let mut discussion = discussion.lock().unwrap();
+        for i in 0..n_speeches {
+            discussion.add_new_speech(format!("Speaker {}", i % n_speakers), i % 3 == 2);
+            if i % 7 == 0 {
+                discussion.tick_clock();
+            }
+        }
+        discussion.set_priority_mode(PriorityMode::FavourBriefest);
+    }
+    return discussion;
+}
+
+fn bench_resort(c: &mut Criterion) {
+    for (group_name, full_resort) in [("tick", false), ("tick and full re-sort", true)] {
+        let mut group = c.benchmark_group(group_name);
+        for n_speakers in [10, 100, 1000] {
+            for n_speeches in [1000, 5000, 20000] {
+                let discussion: Arc<Mutex<Discussion>> = queued_discussion(n_speakers, n_speeches);
+                // Holding the lock keeps the discussion's own clock out
+                let mut discussion = discussion.lock().unwrap();
+                group.bench_function(BenchmarkId::new(format!("{} speakers", n_speakers), n_speeches), |b| b.iter(|| {
+                    discussion.tick_clock();
+                    if full_resort {
+                        discussion.resort_speaking_order();
+                    }
+                }));
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_resort);
+criterion_main!(benches);
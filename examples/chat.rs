@@ -0,0 +1,15 @@
+//! Runs the chat commands over stdin and stdout, one `nick: message` per line.
+//!  Nicks given as arguments join as chairs, e.g.
+//!
+//!     cargo run --example chat --no-default-features -- carol
+
+use std::io;
+
+use speakeasy::chat::{run_lines, ChatBot};
+use speakeasy::discussion::{Discussion, DiscussionSettings};
+
+fn main() -> io::Result<()> {
+    let bot: ChatBot = ChatBot::new(std::env::args().skip(1));
+    let discussion = Discussion::new(DiscussionSettings::default());
+    return run_lines(&bot, &discussion, io::stdin().lock(), io::stdout().lock());
+}
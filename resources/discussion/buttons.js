@@ -1,52 +1,174 @@
-function addSpeech(stype) {
-  const name = document.getElementById("speaker_name").value;
-  fetch(window.location.href + "/add_speaker", {
+function joinDiscussion() {
+  const name = document.getElementById("join_name").value;
+  fetch(window.location.href + "/join", {
     method: "POST",
     headers: {
       'Content-Type': 'application/json'
     },
     body: JSON.stringify({
-      name: name, 
-      stype: stype
+      name: name
     }),
+  }).then(res => {
+    if (res.ok) {
+      refreshSession();
+      connectSocket();
+    } else {
+      res.text().then(message => alert("Could not join: " + message));
+    }
   });
-  refresh();
+}
+
+function addSpeech(stype) {
+  // Only the chair can add speeches for someone else. Everyone else (and the
+  //  chair, if they leave the box empty) adds a speech for themselves
+  const name = document.getElementById("speaker_name").value;
+  const speech = {
+    name: name == "" ? undefined : name,
+    stype: stype
+  };
+  command(Object.assign({command: "add"}, speech), "/add_speaker", speech, "Could not add speaker");
 }
 
 function pointOfOrder() {
-  fetch(window.location.href + "/setpause/pause", {
-    method: "POST",
-  });
-  refresh();
+  command({command: "pause", paused: true}, "/setpause/pause", undefined, "Could not pause");
 }
 
 function resolvePointOfOrder() {
-  fetch(window.location.href + "/setpause/unpause", {
-    method: "POST",
-  });
-  refresh();
+  command({command: "pause", paused: false}, "/setpause/unpause", undefined, "Could not unpause");
 }
 
 function next() {
-  fetch(window.location.href + "/next", {method: "POST"});
-  refresh();
+  command({command: "next"}, "/next", undefined, "Could not move on");
 }
 
 function previous() {
-  fetch(window.location.href + "/previous", {method: "POST"});
+  command({command: "previous"}, "/previous", undefined, "Could not go back");
+}
+
+function undo() {
+  fetch(window.location.href + "/undo", {method: "POST"}).then(res => {
+    if (!res.ok) {
+      res.text().then(message => alert("Could not undo: " + message));
+    }
+  });
   refresh();
 }
 
-function change_priority_mode(option) {
-  fetch(window.location.href + "/set_priority_mode/" + option.value, {method: "POST"});
+function redo() {
+  fetch(window.location.href + "/redo", {method: "POST"}).then(res => {
+    if (!res.ok) {
+      res.text().then(message => alert("Could not redo: " + message));
+    }
+  });
   refresh();
 }
 
+function change_priority_mode(option) {
+  command({command: "set_priority_mode", mode: option.value}, "/set_priority_mode/" + option.value, undefined, "Could not change priority mode");
+}
+
 function aliasSpeakers() {
   const name1 = document.getElementById("name1").value;
   const name2 = document.getElementById("name2").value;
-  fetch(window.location.href + "/alias/" + name1 + "/" + name2, {method: "POST"});
-  document.getElementByIdById("name1").value = "";
-  document.getElementByIdById("name2").value = "";
+  command({command: "alias", name1: name1, name2: name2}, "/alias/" + encodeURIComponent(name1) + "/" + encodeURIComponent(name2), undefined, "Could not alias speakers");
+  document.getElementById("name1").value = "";
+  document.getElementById("name2").value = "";
+}
+
+
+
+function withdrawSpeech() {
+  const id = document.getElementById("withdraw_id").value;
+  if (id === "") {
+    return;
+  }
+  fetch(window.location.href + "/withdraw/" + encodeURIComponent(id), {
+    method: "POST",
+  }).then(res => {
+    if (!res.ok) {
+      res.text().then(message => alert("Could not withdraw speech: " + message));
+    }
+  });
+  refresh();
+}
+
+// Speeches are picked out by the numbers in the first column of the speaking
+//  order, e.g. "3, 7". Leaving them out splits off whatever was added under
+//  the name
+function splitSpeaker() {
+  const name = document.getElementById("split_name").value;
+  const speeches = document.getElementById("split_speeches").value
+    .split(",")
+    .map(id => id.trim().replace(/^#/, ""))
+    .filter(id => id !== "")
+    .map(Number);
+  fetch(window.location.href + "/split/" + encodeURIComponent(name), {
+    method: "POST",
+    headers: {
+      'Content-Type': 'application/json'
+    },
+    body: JSON.stringify(speeches.length > 0 ? { speeches: speeches } : {}),
+  }).then(res => {
+    if (!res.ok) {
+      res.text().then(message => alert("Could not split speaker: " + message));
+    }
+  });
+  refresh();
+}
+
+function assignRole() {
+  const name = document.getElementById("role_name").value;
+  const role = document.getElementById("role").value;
+  fetch(window.location.href + "/role/" + encodeURIComponent(name) + "/" + role, {
+    method: "POST",
+  }).then(res => {
+    if (!res.ok) {
+      res.text().then(message => alert("Could not give role: " + message));
+    }
+  });
+}
+
+// Set from the session report for roles which can share the projector view
+var display_token = null;
+
+function openDisplay() {
+  if (display_token != null) {
+    window.open(window.location.href + "/display/" + display_token);
+  }
+}
+
+function exportDiscussion() {
+  window.open(window.location.href + "/export");
+}
+
+function uploadRoster() {
+  const file = document.getElementById("roster_file").files[0];
+  if (file === undefined) {
+    return;
+  }
+  file.text().then(text => fetch(window.location.href + "/roster", {
+    method: "POST",
+    headers: {
+      'Content-Type': file.name.toLowerCase().endsWith(".json") ? 'application/json' : 'text/csv'
+    },
+    body: text,
+  })).then(res => {
+    if (!res.ok) {
+      res.text().then(message => alert("Could not load roster: " + message));
+    }
+  });
+}
+
+function closeDiscussion() {
+  if (confirm("Are you sure you would like to close this discussion? Nobody will be able to change it afterwards")) {
+    fetch(window.location.href + "/close", {method: "POST"});
+    refresh();
+  }
 }
 
+function archiveDiscussion() {
+  if (confirm("Are you sure you would like to archive this discussion? It will be saved and removed from the server")) {
+    fetch(window.location.href + "/archive", {method: "POST"});
+    refresh();
+  }
+}
\ No newline at end of file
@@ -0,0 +1,73 @@
+// The projector view polls the same status feed as the main view, but lays out
+//  the speeches itself. It lives at /discussion/<id>/display/<token>, so the
+//  feed is found by cutting the display part off the path.
+const status_url = window.location.pathname.split("/display/")[0] + "/status";
+
+// How many upcoming speeches fit on the screen
+const upcoming_shown = 5;
+
+var poll_interval = 1000;
+
+function formatSeconds(seconds) {
+  const sign = seconds < 0 ? "-" : "";
+  seconds = Math.abs(seconds);
+  return sign + Math.floor(seconds / 60) + ":" + String(seconds % 60).padStart(2, "0");
+}
+
+function speechType(speech) {
+  return speech.stype == 1 ? "New point" : "Response";
+}
+
+function render(report) {
+  const current = report.current;
+  if (current) {
+    document.getElementById("current_speaker").innerText = current.speaker;
+    let time = speechType(current) + " - " + formatSeconds(current.duration);
+    if (current.time_limit != null) {
+      time += " (" + formatSeconds(current.time_limit - current.duration) + " left)";
+    }
+    document.getElementById("current_time").innerText = time;
+    document.getElementById("current").classList.toggle("over_time", current.time_limit != null && current.duration > current.time_limit);
+  } else {
+    document.getElementById("current_speaker").innerText = "Nobody is speaking";
+    document.getElementById("current_time").innerText = "";
+    document.getElementById("current").classList.remove("over_time");
+  }
+
+  const upcoming = document.getElementById("upcoming");
+  upcoming.replaceChildren(...report.upcoming.slice(0, upcoming_shown).map(speech => {
+    const item = document.createElement("li");
+    item.innerText = speech.speaker + " (" + speechType(speech).toLowerCase() + ")";
+    return item;
+  }));
+}
+
+function refresh() {
+  fetch(status_url)
+      .then(res => res.json())
+      .then(report => {
+        if (report.poll_interval) {
+          poll_interval = report.poll_interval * 1000;
+        }
+        if (report.status == "Normal") {
+          render(report);
+          document.getElementById("header").innerText = "Speakeasy - " + report.duration;
+          document.getElementById("point_of_order").setAttribute("hidden", "");
+        } else if (report.status == "Paused") {
+          document.getElementById("point_of_order").removeAttribute("hidden");
+        } else if (report.status == "Closed") {
+          render(report);
+          document.getElementById("header").innerText = "Speakeasy - " + report.duration + " (closed)";
+          document.getElementById("point_of_order").setAttribute("hidden", "");
+        }
+      });
+}
+
+function poll() {
+  refresh();
+  setTimeout(poll, poll_interval);
+}
+
+window.onload = (event) => {
+  poll();
+};
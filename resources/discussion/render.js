@@ -1,19 +1,58 @@
+// How long to wait between polls, in milliseconds. The server tells us how
+//  often it is worth asking in each status report
+var poll_interval = 1000;
+
 function refresh() {
   fetch(window.location.href + "/status")
-      .then(res => res.text())
-      .then(data => {
-        const parsed_data = JSON.parse(data);
-        if (parsed_data.status == "Normal") {
-          document.getElementById("speaking_order").innerHTML = parsed_data.speaking_order;
-          document.getElementById("header").innerHTML = "Speakeasy - " + parsed_data.duration;
-          document.getElementById("controls").removeAttribute("hidden");
-          document.getElementById("point_of_order").setAttribute("hidden","");
-        } else if (parsed_data.status == "Paused") {
-          document.getElementById("speaking_order").innerHTML = "";
-          document.getElementById("controls").setAttribute("hidden","");
-          document.getElementById("point_of_order").removeAttribute("hidden");
+      .then(res => res.json())
+      .then(renderReport);
+}
+
+function renderReport(parsed_data) {
+  if (parsed_data.poll_interval) {
+    poll_interval = parsed_data.poll_interval * 1000;
+  }
+  document.getElementById("undo_button").disabled = !parsed_data.can_undo;
+  document.getElementById("redo_button").disabled = !parsed_data.can_redo;
+  if (parsed_data.status == "Normal") {
+    document.getElementById("speaking_order").innerHTML = parsed_data.speaking_order;
+    document.getElementById("header").innerHTML = "Speakeasy - " + parsed_data.duration;
+    document.getElementById("controls").removeAttribute("hidden");
+    document.getElementById("point_of_order").setAttribute("hidden","");
+  } else if (parsed_data.status == "Paused") {
+    document.getElementById("speaking_order").innerHTML = "";
+    document.getElementById("controls").setAttribute("hidden","");
+    document.getElementById("point_of_order").removeAttribute("hidden");
+  } else if (parsed_data.status == "Closed") {
+    document.getElementById("speaking_order").innerHTML = parsed_data.speaking_order;
+    document.getElementById("header").innerHTML = "Speakeasy - " + parsed_data.duration + " (closed)";
+    document.getElementById("controls").setAttribute("hidden","");
+    document.getElementById("point_of_order").setAttribute("hidden","");
+  }
+}
+
+function refreshSession() {
+  fetch(window.location.href + "/session")
+      .then(res => res.json())
+      .then(session => {
+        if (session.name != null || session.is_chair) {
+          document.getElementById("join_controls").setAttribute("hidden", "");
+          document.getElementById("add_speaker_controls").removeAttribute("hidden");
+          document.getElementById("participant_name").innerText = session.name == null ? "" : session.name;
+        } else {
+          document.getElementById("join_controls").removeAttribute("hidden");
+          document.getElementById("add_speaker_controls").setAttribute("hidden", "");
+        }
+        if (session.is_chair) {
+          document.getElementById("speaker_name").removeAttribute("hidden");
+        }
+        display_token = session.display_token;
+        if (display_token != null) {
+          document.getElementById("display_button").removeAttribute("hidden");
+        }
+        if (session.is_chair && session.name == null) {
+          document.getElementById("join_controls").removeAttribute("hidden");
         }
-          
       });
 }
 
@@ -24,7 +63,16 @@ function show_master_controls() {
   }
 }
 
+// Only needed while the control socket is down
+function poll() {
+  if (!socketIsOpen()) {
+    refresh();
+  }
+  setTimeout(poll, poll_interval);
+}
+
 window.onload = (event) => {
-  refresh();
-  setInterval(refresh, 1000);
+  refreshSession();
+  connectSocket();
+  poll();
 };
\ No newline at end of file
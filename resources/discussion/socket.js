@@ -0,0 +1,83 @@
+// Commands go over the discussion's control socket while it is open, and fall
+//  back to the HTTP routes otherwise. The socket also sends every change to
+//  the status report as it happens, so there is no need to poll while it's up.
+var socket = null;
+var socket_report = {};
+var next_command_id = 0;
+const pending_commands = new Map();
+
+function socketIsOpen() {
+  return socket != null && socket.readyState == WebSocket.OPEN;
+}
+
+function giveUpOnCommands() {
+  pending_commands.forEach(resolve => resolve({kind: "Disconnected", message: "lost the connection to the server"}));
+  pending_commands.clear();
+}
+
+// The socket acts with whatever session we had when it connected, so this is
+//  called again after joining
+function connectSocket() {
+  if (socket != null) {
+    socket.close();
+  }
+  const protocol = window.location.protocol == "https:" ? "wss://" : "ws://";
+  const opened = new WebSocket(protocol + window.location.host + window.location.pathname + "/socket");
+  opened.onmessage = event => {
+    const message = JSON.parse(event.data);
+    if (message.type == "state") {
+      Object.assign(socket_report, message.changes);
+      renderReport(socket_report);
+    } else if (message.type == "result") {
+      const resolve = pending_commands.get(message.id);
+      pending_commands.delete(message.id);
+      if (resolve) {
+        resolve(message.error);
+      }
+    }
+  };
+  opened.onclose = () => {
+    if (socket !== opened) {
+      return;
+    }
+    socket = null;
+    socket_report = {};
+    giveUpOnCommands();
+    setTimeout(connectSocket, 5000);
+  };
+  socket = opened;
+  giveUpOnCommands();
+}
+
+// Resolves to null if the command worked, or to the error the server sent back
+function sendCommand(command) {
+  const id = next_command_id++;
+  return new Promise(resolve => {
+    pending_commands.set(id, resolve);
+    socket.send(JSON.stringify(Object.assign({id: id}, command)));
+  });
+}
+
+// Sends `command` over the socket if it is open, or POSTs `body` (if any) to
+//  `route` if not. Either way, `failure` starts the message shown if it's refused.
+function command(command, route, body, failure) {
+  if (socketIsOpen()) {
+    sendCommand(command).then(error => {
+      if (error != null) {
+        alert(failure + ": " + error.message);
+      }
+    });
+    return;
+  }
+  const options = {method: "POST"};
+  if (body !== undefined) {
+    options.headers = {'Content-Type': 'application/json'};
+    options.body = JSON.stringify(body);
+  }
+  fetch(window.location.href + route, options).then(res => {
+    if (!res.ok) {
+      res.text().then(message => alert(failure + ": " + message));
+    }
+    refresh();
+  });
+}
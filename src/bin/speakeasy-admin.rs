@@ -0,0 +1,250 @@
+// Operates a running Speakeasy server through its HTTP API, e.g.
+//
+//     speakeasy-admin --server http://localhost:8000 --token $TOKEN list
+//     speakeasy-admin pause board
+//     speakeasy-admin --json stats board > board-stats.json
+//
+// Everything but `show` needs the server's `admin_token`. Output is meant for
+//  people unless `--json` is given, in which case it is whatever the server
+//  sent back, for scripts.
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use serde_json::Value;
+
+#[derive(Parser)]
+#[command(name = "speakeasy-admin", version, about = "Inspect and operate a running Speakeasy server")]
+struct Cli {
+    /// Where the server is listening
+    #[arg(long, env = "SPEAKEASY_SERVER", default_value = "http://localhost:8000")]
+    server: String,
+    /// The server's `admin_token`
+    #[arg(long, env = "SPEAKEASY_ADMIN_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Print the server's JSON instead of a summary
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List every discussion the server is holding
+    List,
+    /// Show a discussion's status and speaking order
+    Show { id: String },
+    /// Move a discussion on to the next speech
+    Next { id: String },
+    /// Go back to the previous speech
+    Previous { id: String },
+    /// Raise a point of order
+    Pause { id: String },
+    /// Resolve the point of order
+    Resume { id: String },
+    /// Print every speech given or queued, in order
+    Minutes { id: String },
+    /// Print how long each speaker has spoken for
+    Stats { id: String },
+    /// Print the full record of a discussion, as it would be archived
+    Export { id: String },
+    /// Close a discussion, so nothing in it can change
+    Close { id: String },
+    /// Archive a discussion to the server's data directory and remove it
+    Archive { id: String },
+}
+
+struct Server {
+    base: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl Server {
+
+    fn request(&self, method: &str, path: &str) -> Result<String, String> {
+        let mut request = self.agent.request(method, &format!("{}{}", self.base.trim_end_matches('/'), path));
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        match request.call() {
+            Ok(response) => return response.into_string().map_err(|e| e.to_string()),
+            Err(ureq::Error::Status(status, response)) => {
+                let message: String = response.into_string().unwrap_or_default();
+                return Err(format!("the server refused ({}): {}", status, message));
+            },
+            Err(e) => return Err(format!("could not reach the server: {}", e)),
+        }
+    }
+
+    fn get_json(&self, path: &str) -> Result<Value, String> {
+        let body: String = self.request("GET", path)?;
+        return serde_json::from_str(&body).map_err(|e| format!("the server sent something unexpected: {}", e));
+    }
+
+}
+
+fn path(id: &str, action: &str) -> String {
+    let mut path: String = String::from("/discussion/");
+    for byte in id.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => path.push(byte as char),
+            _ => path.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    if !action.is_empty() {
+        path.push('/');
+        path.push_str(action);
+    }
+    return path;
+}
+
+fn minutes_and_seconds(seconds: &Value) -> String {
+    let seconds: u64 = seconds.as_u64().unwrap_or_default();
+    return format!("{}:{:02}", seconds / 60, seconds % 60);
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => return text.clone(),
+        Value::Null => return "-".to_string(),
+        _ => return value.to_string(),
+    }
+}
+
+fn speech_type(stype: &Value) -> &'static str {
+    match stype.as_u64() {
+        Some(1) => return "new point",
+        Some(2) => return "response",
+        _ => return "?",
+    }
+}
+
+fn print_list(discussions: &Value) {
+    println!("{:<24} {:<9} {:>12} {:>7} {:>9} {:>9}", "ID", "STATE", "PARTICIPANTS", "QUEUED", "DURATION", "IDLE");
+    for discussion in discussions.as_array().into_iter().flatten() {
+        let state: String = match (text(&discussion["lifecycle"]).as_str(), discussion["paused"].as_bool()) {
+            ("Open", Some(true)) => "paused".to_string(),
+            (lifecycle, _) => lifecycle.to_lowercase(),
+        };
+        println!("{:<24} {:<9} {:>12} {:>7} {:>9} {:>9}",
+            text(&discussion["id"]),
+            state,
+            text(&discussion["participants"]),
+            text(&discussion["queued_speeches"]),
+            minutes_and_seconds(&discussion["duration"]),
+            minutes_and_seconds(&discussion["idle_for"]),
+        );
+    }
+}
+
+fn print_status(id: &str, status: &Value) {
+    println!("{} is {} ({})", id, text(&status["status"]).to_lowercase(), text(&status["duration"]));
+    let current: &Value = &status["current"];
+    if !current.is_null() {
+        println!("Speaking now: #{} {} ({}, {})", text(&current["id"]), text(&current["speaker"]), speech_type(&current["stype"]), minutes_and_seconds(&current["duration"]));
+    }
+    for speech in status["upcoming"].as_array().into_iter().flatten() {
+        println!("  then #{} {} ({})", text(&speech["id"]), text(&speech["speaker"]), speech_type(&speech["stype"]));
+    }
+}
+
+fn print_minutes(record: &Value) {
+    println!("{:>6} {:<24} {:<10} {:>8}  QUEUED AT", "SPEECH", "SPEAKER", "TYPE", "LENGTH");
+    for speech in record["speeches"].as_array().into_iter().flatten() {
+        println!("{:>6} {:<24} {:<10} {:>8}  {}",
+            format!("#{}", text(&speech["id"])),
+            text(&speech["speaker"]),
+            speech_type(&speech["stype"]),
+            minutes_and_seconds(&speech["duration"]),
+            text(&speech["queued_at"]),
+        );
+    }
+}
+
+fn print_stats(record: &Value) {
+    println!("{:<24} {:>8} {:>10}  ALSO KNOWN AS", "SPEAKER", "SPEECHES", "TIME");
+    let mut speakers: Vec<&Value> = record["speakers"].as_array().into_iter().flatten().collect();
+    speakers.sort_by_key(|speaker| std::cmp::Reverse(speaker["total_speaking_time"].as_u64()));
+    for speaker in speakers {
+        let aliases: Vec<String> = speaker["aliases"].as_array().into_iter().flatten().map(text).collect();
+        println!("{:<24} {:>8} {:>10}  {}",
+            text(&speaker["name"]),
+            text(&speaker["number_of_speeches_given"]),
+            minutes_and_seconds(&speaker["total_speaking_time"]),
+            aliases.join(", "),
+        );
+    }
+    println!("Discussion ran for {}", minutes_and_seconds(&record["duration"]));
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+
+    let server: Server = Server {
+        base: cli.server,
+        token: cli.token,
+        agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(30)).build(),
+    };
+
+    match &cli.command {
+        Command::List => {
+            let discussions: Value = server.get_json("/admin/discussions")?;
+            if cli.json { println!("{}", discussions); } else { print_list(&discussions); }
+        },
+        Command::Show { id } => {
+            let status: Value = server.get_json(&path(id, "status"))?;
+            if status["status"] == "NonExistant" {
+                return Err(format!("there is no discussion called {:?}", id));
+            }
+            if cli.json { println!("{}", status); } else { print_status(id, &status); }
+        },
+        Command::Minutes { id } => {
+            let record: Value = server.get_json(&path(id, "export"))?;
+            if cli.json { println!("{}", record["speeches"]); } else { print_minutes(&record); }
+        },
+        Command::Stats { id } => {
+            let record: Value = server.get_json(&path(id, "export"))?;
+            if cli.json { println!("{}", record["speakers"]); } else { print_stats(&record); }
+        },
+        Command::Export { id } => {
+            let record: Value = server.get_json(&path(id, "export"))?;
+            println!("{}", serde_json::to_string_pretty(&record).unwrap_or_default());
+        },
+        Command::Next { id } => change(&server, id, "next", "Moved on in", cli.json)?,
+        Command::Previous { id } => change(&server, id, "previous", "Went back in", cli.json)?,
+        Command::Pause { id } => change(&server, id, "setpause/pause", "Paused", cli.json)?,
+        Command::Resume { id } => change(&server, id, "setpause/unpause", "Resumed", cli.json)?,
+        Command::Close { id } => change(&server, id, "close", "Closed", cli.json)?,
+        Command::Archive { id } => change(&server, id, "archive", "Archived", cli.json)?,
+    }
+    return Ok(());
+}
+
+fn change(server: &Server, id: &str, action: &str, done: &str, json: bool) -> Result<(), String> {
+    server.request("POST", &path(id, action))?;
+    if json {
+        println!("{}", serde_json::json!({ "id": id, "done": action }));
+    } else {
+        println!("{} {}", done, id);
+    }
+    return Ok(());
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => return ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("speakeasy-admin: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+}
+
+#[test]
+fn test_ids_are_escaped() {
+    assert_eq!(path("board", "status"), "/discussion/board/status");
+    assert_eq!(path("a b/c", ""), "/discussion/a%20b%2Fc");
+    assert_eq!(minutes_and_seconds(&serde_json::json!(125)), "2:05");
+}
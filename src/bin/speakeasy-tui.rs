@@ -0,0 +1,548 @@
+// A terminal client for chairs, e.g.
+//
+//     speakeasy-tui --server https://speakeasy.example.org --token $TOKEN board
+//     speakeasy-tui --session $COOKIE board
+//     speakeasy-tui --local --priority-mode brevity --new-point-limit 120 committee
+//
+// It either drives a discussion on a running server, through the same HTTP API
+//  as the browser, or holds a discussion of its own in memory for meetings
+//  with no server at all. On a server it acts with the server's `admin_token`,
+//  or with a chair's session (the `speakeasy_session` cookie from the browser
+//  which created the discussion). A local discussion is closed when the TUI is
+//  quit, and its record (the minutes and everyone's speaking time) is written
+//  to `--data-dir` in the same format as the server's archives.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, ListItem, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use serde_json::Value;
+
+use speakeasy::discussion::{AddSpeechResult, Discussion, DiscussionSettings, GotoSpeechResult, PriorityMode};
+use speakeasy::discussion::speech::SpeechId;
+use speakeasy::format_duration::format_duration_m_s;
+use speakeasy::storage;
+
+#[derive(Parser)]
+#[command(name = "speakeasy-tui", version, about = "Chair a Speakeasy discussion from the terminal")]
+struct Cli {
+    /// Where the server is listening
+    #[arg(long, env = "SPEAKEASY_SERVER", default_value = "http://localhost:8000")]
+    server: String,
+    /// The server's `admin_token`
+    #[arg(long, env = "SPEAKEASY_ADMIN_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// A chair's session cookie for the discussion
+    #[arg(long, env = "SPEAKEASY_SESSION", hide_env_values = true)]
+    session: Option<String>,
+    /// Run a discussion in this process instead of connecting to a server
+    #[arg(long, conflicts_with_all = ["token", "session"])]
+    local: bool,
+    /// How a local discussion orders new points: `fcfs`, `brevity` or `shy`
+    #[arg(long, requires = "local", value_parser = priority_mode, default_value = "fcfs")]
+    priority_mode: PriorityMode,
+    /// The time limit for new points in a local discussion, in seconds
+    #[arg(long, requires = "local")]
+    new_point_limit: Option<u64>,
+    /// The time limit for responses in a local discussion, in seconds
+    #[arg(long, requires = "local")]
+    response_limit: Option<u64>,
+    /// Where the record of a local discussion is written when it ends
+    #[arg(long, requires = "local", default_value = ".")]
+    data_dir: PathBuf,
+    /// The discussion to chair (for a local discussion, the name its record is
+    ///  saved under)
+    #[arg(required_unless_present = "local")]
+    id: Option<String>,
+}
+
+// Accepts the same names for the priority modes as the server's settings
+fn priority_mode(text: &str) -> Result<PriorityMode, String> {
+    return serde_json::from_value(Value::String(text.to_string())).map_err(|_| format!("{:?} isn't a priority mode (try fcfs, brevity or shy)", text));
+}
+
+// How often a server is asked for the speaking order. The local discussion is
+//  read on every redraw instead.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Row {
+    id: usize,
+    speaker: String,
+    is_response: bool,
+    duration: u64,
+    time_limit: Option<u64>,
+}
+
+#[derive(Default)]
+struct View {
+    is_paused: bool,
+    is_closed: bool,
+    duration: String,
+    current: Option<Row>,
+    upcoming: Vec<Row>,
+}
+
+enum Change {
+    Next,
+    Previous,
+    SetPaused(bool),
+    Add { name: String, is_response: bool },
+    Alias(String, String),
+}
+
+// What the keys are bound to, whether the discussion is here or on a server
+trait Backend {
+    fn title(&self) -> String;
+    fn view(&mut self) -> Result<View, String>;
+    fn change(&mut self, change: Change) -> Result<(), String>;
+    // Called on the way out, with whatever should be said once the terminal is
+    //  back to normal
+    fn finish(&mut self) -> Result<Option<String>, String> {
+        return Ok(None);
+    }
+}
+
+struct Local {
+    id: String,
+    discussion: Arc<Mutex<Discussion>>,
+    data_dir: PathBuf,
+}
+
+impl Backend for Local {
+
+    fn title(&self) -> String {
+        return format!("{} (local)", self.id);
+    }
+
+    fn finish(&mut self) -> Result<Option<String>, String> {
+        let mut discussion = self.discussion.lock().map_err(|e| e.to_string())?;
+        discussion.close();
+        match storage::write_archive(&self.data_dir, &self.id, &discussion) {
+            Ok(path) => return Ok(Some(format!("The minutes and speaking times are in {}", path.display()))),
+            Err(e) => return Err(format!("could not write the minutes to {}: {}", self.data_dir.display(), e)),
+        }
+    }
+
+    fn view(&mut self) -> Result<View, String> {
+        let discussion = self.discussion.lock().map_err(|e| e.to_string())?;
+        let current_speech: Option<SpeechId> = discussion.current_speech();
+        let mut view: View = View {
+            is_paused: discussion.paused,
+            is_closed: discussion.is_frozen(),
+            duration: format_duration_m_s(&discussion.duration),
+            ..View::default()
+        };
+        let mut add_row = |id: &SpeechId, is_response: bool| {
+            let speech = match discussion.speeches.get(*id) {
+                Some(speech) => speech,
+                None => return,
+            };
+            let time_limit: Option<Duration> = if is_response { discussion.response_time_limit } else { discussion.new_point_time_limit };
+            let row: Row = Row {
+                id: id.0,
+                speaker: match speech.speaker.lock() {
+                    Ok(speaker) => speaker.name.clone(),
+                    Err(e) => e.into_inner().name.clone(),
+                },
+                is_response: is_response,
+                duration: speech.duration.as_secs(),
+                time_limit: time_limit.map(|limit| limit.as_secs()),
+            };
+            if current_speech == Some(*id) {
+                view.current = Some(row);
+            } else {
+                view.upcoming.push(row);
+            }
+        };
+        if let Some(current_new_point) = &discussion.current_new_point {
+            add_row(current_new_point, false);
+        }
+        for response in &discussion.first_response_block {
+            add_row(response, true);
+        }
+        for (new_point, responses) in &discussion.upcoming_speeches {
+            add_row(new_point, false);
+            for response in responses {
+                add_row(response, true);
+            }
+        }
+        return Ok(view);
+    }
+
+    fn change(&mut self, change: Change) -> Result<(), String> {
+        let mut discussion = self.discussion.lock().map_err(|e| e.to_string())?;
+        match change {
+            Change::Next => match discussion.goto_next_speech() {
+                GotoSpeechResult::Success => (),
+                GotoSpeechResult::NoSpeechToGoTo => return Err("nobody is waiting to speak".to_string()),
+                GotoSpeechResult::DiscussionIsFrozen => return Err("the discussion is closed".to_string()),
+                GotoSpeechResult::IllegalDiscussionSomehow => return Err("the speaking order is in a state it shouldn't be".to_string()),
+            },
+            Change::Previous => discussion.goto_previous_speech(),
+            Change::SetPaused(paused) => discussion.set_paused(paused),
+            Change::Add { name, is_response } => match discussion.add_new_speech(name, is_response) {
+                AddSpeechResult::Success => (),
+                AddSpeechResult::NothingToRespondTo => return Err("there is nothing to respond to".to_string()),
+                AddSpeechResult::TooManyQueuedSpeeches => return Err("they already have as many speeches queued as they're allowed".to_string()),
+                AddSpeechResult::EmptyName => return Err("speakers need a name".to_string()),
+                AddSpeechResult::DiscussionIsFrozen => return Err("the discussion is closed".to_string()),
+                AddSpeechResult::NotJoined | AddSpeechResult::OnlyChairCanAddForOthers => return Err("not allowed".to_string()),
+            },
+            Change::Alias(name1, name2) => discussion.alias_speakers(&name1, &name2),
+        }
+        return Ok(());
+    }
+
+}
+
+struct Remote {
+    base: String,
+    id: String,
+    token: Option<String>,
+    session: Option<String>,
+    agent: ureq::Agent,
+}
+
+// Escapes one segment of a URL path
+fn segment(text: &str) -> String {
+    let mut escaped: String = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    return escaped;
+}
+
+fn row(speech: &Value) -> Row {
+    return Row {
+        id: speech["id"].as_u64().unwrap_or_default() as usize,
+        speaker: speech["speaker"].as_str().unwrap_or_default().to_string(),
+        is_response: speech["stype"] == 2,
+        duration: speech["duration"].as_u64().unwrap_or_default(),
+        time_limit: speech["time_limit"].as_u64(),
+    };
+}
+
+impl Remote {
+
+    fn request(&self, method: &str, action: &str) -> ureq::Request {
+        let mut request = self.agent.request(method, &format!("{}/discussion/{}/{}", self.base.trim_end_matches('/'), segment(&self.id), action));
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        if let Some(session) = &self.session {
+            request = request.set("Cookie", &format!("speakeasy_session={}", session));
+        }
+        return request;
+    }
+
+    fn send(request: ureq::Request, body: Option<Value>) -> Result<String, String> {
+        let response = match body {
+            Some(body) => request.set("Content-Type", "application/json").send_string(&body.to_string()),
+            None => request.call(),
+        };
+        match response {
+            Ok(response) => return response.into_string().map_err(|e| e.to_string()),
+            Err(ureq::Error::Status(status, response)) => {
+                let message: String = response.into_string().unwrap_or_default();
+                return Err(format!("the server refused ({}): {}", status, message));
+            },
+            Err(e) => return Err(format!("could not reach the server: {}", e)),
+        }
+    }
+
+}
+
+impl Backend for Remote {
+
+    fn title(&self) -> String {
+        return format!("{} on {}", self.id, self.base);
+    }
+
+    fn view(&mut self) -> Result<View, String> {
+        let body: String = Remote::send(self.request("GET", "status"), None)?;
+        let status: Value = serde_json::from_str(&body).map_err(|e| format!("the server sent something unexpected: {}", e))?;
+        if status["status"] == "NonExistant" {
+            return Err(format!("there is no discussion called {:?}", self.id));
+        }
+        return Ok(View {
+            is_paused: status["status"] == "Paused",
+            is_closed: status["status"] == "Closed",
+            duration: status["duration"].as_str().unwrap_or_default().to_string(),
+            current: if status["current"].is_null() { None } else { Some(row(&status["current"])) },
+            upcoming: status["upcoming"].as_array().into_iter().flatten().map(row).collect(),
+        });
+    }
+
+    fn change(&mut self, change: Change) -> Result<(), String> {
+        match change {
+            Change::Next => Remote::send(self.request("POST", "next"), None)?,
+            Change::Previous => Remote::send(self.request("POST", "previous"), None)?,
+            Change::SetPaused(paused) => Remote::send(self.request("POST", if paused { "setpause/pause" } else { "setpause/unpause" }), None)?,
+            Change::Add { name, is_response } => {
+                let body: Value = serde_json::json!({ "name": name, "stype": if is_response { 2 } else { 1 } });
+                Remote::send(self.request("POST", "add_speaker"), Some(body))?
+            },
+            Change::Alias(name1, name2) => Remote::send(self.request("POST", &format!("alias/{}/{}", segment(&name1), segment(&name2))), None)?,
+        };
+        return Ok(());
+    }
+
+}
+
+// What the next line typed at the prompt is for
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    NewPoint,
+    Response,
+    Alias,
+}
+
+struct App {
+    backend: Box<dyn Backend>,
+    view: View,
+    prompt: Option<(Prompt, String)>,
+    message: Option<String>,
+    refreshed_at: Option<Instant>,
+}
+
+const KEYS: &str = "n next  p previous  space pause/resume  a new point  r response  l alias  q quit";
+
+impl App {
+
+    fn refresh(&mut self) {
+        match self.backend.view() {
+            Ok(view) => self.view = view,
+            Err(e) => self.message = Some(e),
+        }
+        self.refreshed_at = Some(Instant::now());
+    }
+
+    fn change(&mut self, change: Change) {
+        self.message = self.backend.change(change).err();
+        self.refresh();
+    }
+
+    // Returns false once it's time to quit
+    fn key(&mut self, key: KeyCode) -> bool {
+
+        if let Some((prompt, mut text)) = self.prompt.take() {
+            match key {
+                KeyCode::Esc => (),
+                KeyCode::Enter => match prompt {
+                    Prompt::NewPoint | Prompt::Response => self.change(Change::Add { name: text.trim().to_string(), is_response: prompt == Prompt::Response }),
+                    Prompt::Alias => match text.split_once('=') {
+                        Some((name1, name2)) => self.change(Change::Alias(name1.trim().to_string(), name2.trim().to_string())),
+                        None => self.message = Some("aliases are written as `name = other name`".to_string()),
+                    },
+                },
+                KeyCode::Backspace => {
+                    text.pop();
+                    self.prompt = Some((prompt, text));
+                },
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    self.prompt = Some((prompt, text));
+                },
+                _ => self.prompt = Some((prompt, text)),
+            }
+            return true;
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('n') | KeyCode::Right => self.change(Change::Next),
+            KeyCode::Char('p') | KeyCode::Left => self.change(Change::Previous),
+            KeyCode::Char(' ') => self.change(Change::SetPaused(!self.view.is_paused)),
+            KeyCode::Char('a') => self.prompt = Some((Prompt::NewPoint, String::new())),
+            KeyCode::Char('r') => self.prompt = Some((Prompt::Response, String::new())),
+            KeyCode::Char('l') => self.prompt = Some((Prompt::Alias, String::new())),
+            _ => (),
+        }
+        return true;
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+
+        let [header, current, upcoming, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(4),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ]).areas(frame.area());
+
+        let state: Span = if self.view.is_closed {
+            Span::styled(" CLOSED ", Style::new().fg(Color::Black).bg(Color::Gray))
+        } else if self.view.is_paused {
+            Span::styled(" POINT OF ORDER ", Style::new().fg(Color::Black).bg(Color::Yellow))
+        } else {
+            Span::raw("")
+        };
+        frame.render_widget(Paragraph::new(Line::from(vec![
+            Span::styled(self.backend.title(), Style::new().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("  {}  ", self.view.duration)),
+            state,
+        ])), header);
+
+        let block: Block = Block::bordered().title("Speaking now");
+        match &self.view.current {
+            Some(speech) => {
+                let label: String = format!("#{} {} ({}) {}", speech.id, speech.speaker, speech_type(speech.is_response), time(speech.duration, speech.time_limit));
+                let (ratio, colour) = match speech.time_limit {
+                    Some(limit) if speech.duration > limit => (1.0, Color::Red),
+                    Some(limit) if limit > 0 => (speech.duration as f64 / limit as f64, Color::Green),
+                    _ => (0.0, Color::Green),
+                };
+                frame.render_widget(Gauge::default().block(block).gauge_style(Style::new().fg(colour)).ratio(ratio).label(label), current);
+            },
+            None => frame.render_widget(Paragraph::new("Nobody").block(block), current),
+        }
+
+        let rows: Vec<ListItem> = self.view.upcoming.iter().map(|speech| {
+            let indent: &str = if speech.is_response { "    " } else { "" };
+            return ListItem::new(format!("{}#{} {} ({})", indent, speech.id, speech.speaker, speech_type(speech.is_response)));
+        }).collect();
+        frame.render_widget(List::new(rows).block(Block::bordered().title("Up next")), upcoming);
+
+        let footer_text: String = match (&self.prompt, &self.message) {
+            (Some((Prompt::NewPoint, text)), _) => format!("New point from: {}_", text),
+            (Some((Prompt::Response, text)), _) => format!("Response from: {}_", text),
+            (Some((Prompt::Alias, text)), _) => format!("Alias (name = other name): {}_", text),
+            (None, Some(message)) => message.clone(),
+            (None, None) => KEYS.to_string(),
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+
+}
+
+fn speech_type(is_response: bool) -> &'static str {
+    if is_response {
+        return "response";
+    } else {
+        return "new point";
+    }
+}
+
+fn time(duration: u64, time_limit: Option<u64>) -> String {
+    let m_s = |seconds: u64| format_duration_m_s(&Duration::from_secs(seconds));
+    match time_limit {
+        Some(limit) => return format!("{} / {}", m_s(duration), m_s(limit)),
+        None => return m_s(duration),
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> std::io::Result<()> {
+    loop {
+        let is_stale: bool = app.refreshed_at.is_none_or(|refreshed_at| refreshed_at.elapsed() >= REFRESH_INTERVAL);
+        if is_stale {
+            app.refresh();
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+        if event::poll(Duration::from_millis(250))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !app.key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+
+    let cli: Cli = Cli::parse();
+    let backend: Box<dyn Backend> = match (cli.local, cli.id) {
+        (true, id) => Box::new(Local {
+            id: id.unwrap_or_else(|| "meeting".to_string()),
+            discussion: Discussion::new(DiscussionSettings {
+                priority_mode: cli.priority_mode,
+                new_point_time_limit: cli.new_point_limit.map(Duration::from_secs),
+                response_time_limit: cli.response_limit.map(Duration::from_secs),
+                ..DiscussionSettings::default()
+            }),
+            data_dir: cli.data_dir,
+        }),
+        (false, Some(id)) => Box::new(Remote {
+            base: cli.server,
+            id: id,
+            token: cli.token,
+            session: cli.session,
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(10)).build(),
+        }),
+        (false, None) => {
+            eprintln!("speakeasy-tui: give a discussion to chair, or --local");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut app: App = App { backend: backend, view: View::default(), prompt: None, message: None, refreshed_at: None };
+    let mut terminal: DefaultTerminal = ratatui::init();
+    let result: std::io::Result<()> = run(&mut terminal, &mut app);
+    ratatui::restore();
+
+    if let Err(e) = result {
+        eprintln!("speakeasy-tui: {}", e);
+    }
+    // Even when the terminal gave out, a local meeting's minutes are worth saving
+    match app.backend.finish() {
+        Ok(Some(message)) => println!("{}", message),
+        Ok(None) => (),
+        Err(e) => {
+            eprintln!("speakeasy-tui: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    return ExitCode::SUCCESS;
+}
+
+#[test]
+fn test_keys_drive_a_local_discussion() {
+    let mut app: App = App {
+        backend: Box::new(Local {
+            id: "tui-test".to_string(),
+            discussion: Discussion::new(DiscussionSettings::default()),
+            data_dir: std::env::temp_dir().join("speakeasy-tui-test"),
+        }),
+        view: View::default(),
+        prompt: None,
+        message: None,
+        refreshed_at: None,
+    };
+    fn type_in(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.key(if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) });
+        }
+    }
+    type_in(&mut app, "aAda\n");
+    type_in(&mut app, "rAlan\n");
+    type_in(&mut app, "aBea\n");
+    type_in(&mut app, "lBea = Beatrice\n");
+    type_in(&mut app, "n ");
+    assert!(app.view.is_paused);
+    assert_eq!(app.view.current.as_ref().map(|speech| speech.speaker.as_str()), Some("Alan"));
+    assert_eq!(app.view.upcoming.iter().map(|speech| speech.id).collect::<Vec<usize>>(), vec![2]);
+    type_in(&mut app, " pp");
+    assert!(!app.view.is_paused);
+    assert_eq!(app.view.current.as_ref().map(|speech| speech.id), Some(0));
+    assert_eq!(app.message, None);
+    assert!(!app.key(KeyCode::Char('q')));
+
+    let message: String = app.backend.finish().unwrap().unwrap();
+    let path: &str = message.rsplit(' ').next().unwrap();
+    let record: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(record["lifecycle"], "Closed");
+    assert_eq!(record["speeches"].as_array().map(Vec::len), Some(3));
+    assert!(record["speakers"].as_array().unwrap().iter().any(|speaker| speaker["aliases"][0] == "Beatrice"));
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,347 @@
+//! Drives a discussion from chat, for meetings where some people are only in
+//! a chat room. [`ChatBot`] reads messages like `!new` or `stack me` and turns
+//! them into changes to a [`Discussion`], then words a reply to send back.
+//!
+//! Nothing here knows about any particular chat service. A bridge only has to
+//! pass each message in with a stable ID and a display name for whoever sent
+//! it, and post the reply. [`run_lines`] is the simplest possible bridge,
+//! reading IRC-style `nick: message` lines, e.g. from a terminal.
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use std::sync::Mutex;
+
+use debug_panic::debug_panic;
+
+use crate::discussion::{AddSpeechResult, Discussion, GotoSpeechResult, JoinResult, WithdrawSpeechResult};
+use crate::discussion::participant::Action;
+use crate::discussion::speech::SpeechId;
+use crate::format_duration::format_duration_m_s;
+
+/// Whoever sent a message. `id` has to stay the same for as long as the
+///  discussion runs (a user ID rather than a nickname, where the service has
+///  one); `name` is what they are called in the speaking order.
+pub struct ChatUser<'a> {
+    pub id: &'a str,
+    pub name: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatCommand {
+    NewPoint,
+    Response,
+    // Without an ID, the sender's latest speech which is still waiting
+    Withdraw(Option<SpeechId>),
+    Queue,
+    PointOfOrder,
+    Resume,
+    Next,
+    Help,
+}
+
+const HELP: &str = "!new (or \"stack me\") to make a new point, !response to respond to the current one, \
+    !withdraw [#speech] to take a speech back, !queue to see the speaking order, !poo to raise a point of order. \
+    Chairs also have !next and !resume.";
+
+impl ChatCommand {
+
+    /// Reads a message as a command. Ordinary chatter, and `!` commands meant
+    ///  for other bots, give `None` so that the bot can sit in a busy room.
+    pub fn parse(message: &str) -> Option<Result<Self, String>> {
+
+        let message: String = message.trim().to_lowercase();
+        if message == "stack me" {
+            return Some(Ok(ChatCommand::NewPoint));
+        }
+
+        let mut words = message.strip_prefix('!')?.split_whitespace();
+        let command: ChatCommand = match words.next()? {
+            "new" | "stack" => ChatCommand::NewPoint,
+            "response" | "respond" | "r" => ChatCommand::Response,
+            "withdraw" => match words.next() {
+                Some(speech) => match speech.trim_start_matches('#').parse::<usize>() {
+                    Ok(id) => ChatCommand::Withdraw(Some(SpeechId(id))),
+                    Err(_) => return Some(Err(format!("{:?} isn't a speech number", speech))),
+                },
+                None => ChatCommand::Withdraw(None),
+            },
+            "queue" | "q" => ChatCommand::Queue,
+            "poo" => ChatCommand::PointOfOrder,
+            "resume" => ChatCommand::Resume,
+            "next" => ChatCommand::Next,
+            "help" => ChatCommand::Help,
+            _ => return None,
+        };
+        return Some(Ok(command));
+    }
+
+}
+
+/// Answers chat commands for one discussion. Everyone who sends a command
+///  joins the discussion under their display name; the users in `chairs` join
+///  as chairs.
+pub struct ChatBot {
+    chairs: HashSet<String>,
+}
+
+fn speaker_of(discussion: &Discussion, id: SpeechId) -> String {
+    match discussion.speeches.get(id).map(|speech| speech.speaker.lock()) {
+        Some(Ok(speaker)) => return speaker.name.clone(),
+        Some(Err(e)) => return e.into_inner().name.clone(),
+        None => {
+            debug_panic!();
+            return String::new();
+        }
+    }
+}
+
+fn describe(discussion: &Discussion, id: SpeechId, is_response: bool) -> String {
+    return format!("#{} {} ({})", id.0, speaker_of(discussion, id), if is_response { "response" } else { "new point" });
+}
+
+/// The speaking order as one line, e.g. `Now: #0 Ada (new point, 1:02). Then:
+///  #1 Alan (response), #2 Bea (new point).`
+pub fn format_queue(discussion: &Discussion) -> String {
+
+    let mut waiting: Vec<(SpeechId, bool)> = Vec::new();
+    if let Some(new_point) = discussion.current_new_point {
+        waiting.push((new_point, false));
+    }
+    waiting.extend(discussion.first_response_block.iter().map(|response| (*response, true)));
+    for (new_point, responses) in &discussion.upcoming_speeches {
+        waiting.push((*new_point, false));
+        waiting.extend(responses.iter().map(|response| (*response, true)));
+    }
+
+    let mut line: String = String::new();
+    if discussion.is_frozen() {
+        line.push_str("This discussion has been closed. ");
+    } else if discussion.paused {
+        line.push_str("Paused for a point of order. ");
+    }
+
+    let current: Option<SpeechId> = discussion.current_speech();
+    let mut upcoming = waiting.iter().peekable();
+    if let Some((id, is_response)) = upcoming.next_if(|(id, _)| Some(*id) == current) {
+        let duration: String = discussion.speeches.get(*id).map(|speech| format_duration_m_s(&speech.duration)).unwrap_or_default();
+        let now: String = describe(discussion, *id, *is_response);
+        line.push_str(&format!("Now: {}, {}). ", now.trim_end_matches(')'), duration));
+    }
+    let then: Vec<String> = upcoming.map(|(id, is_response)| describe(discussion, *id, *is_response)).collect();
+    if !then.is_empty() {
+        line.push_str(&format!("Then: {}.", then.join(", ")));
+    }
+
+    if waiting.is_empty() {
+        line.push_str("Nobody is speaking or waiting to speak.");
+    }
+    return line.trim_end().to_string();
+}
+
+impl ChatBot {
+
+    pub fn new<S: Into<String>>(chairs: impl IntoIterator<Item = S>) -> Self {
+        ChatBot {
+            chairs: chairs.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    // Chat users get a session of their own, which can't clash with the
+    //  random tokens handed out to browsers
+    fn token(user: &ChatUser) -> String {
+        return format!("chat:{}", user.id);
+    }
+
+    fn join(&self, discussion: &mut Discussion, user: &ChatUser) -> Result<String, String> {
+        let token: String = ChatBot::token(user);
+        if self.chairs.contains(user.id) && !discussion.participants.contains_key(&token) {
+            discussion.add_chair(token.clone());
+        }
+        match discussion.join(token.clone(), user.name.to_string()) {
+            JoinResult::Success | JoinResult::AlreadyJoined => return Ok(token),
+            JoinResult::NameTaken => return Err(format!("somebody else has already joined as {}", user.name)),
+            JoinResult::DiscussionIsFrozen => return Err("this discussion has been closed".to_string()),
+        }
+    }
+
+    fn allowed(discussion: &Discussion, token: &str, action: Action) -> Result<(), String> {
+        match discussion.role_of(token) {
+            Some(role) if role.can(action) => return Ok(()),
+            _ => return Err("only the chair can do that".to_string()),
+        }
+    }
+
+    /// Carries out `message` if it is a command, and returns the reply to post.
+    ///  Messages which aren't commands get no reply.
+    pub fn handle(&self, discussion: &mut Discussion, user: &ChatUser, message: &str) -> Option<String> {
+        let reply: Result<String, String> = match ChatCommand::parse(message)? {
+            Ok(command) => self.run(discussion, user, command),
+            Err(e) => Err(e),
+        };
+        match reply {
+            Ok(reply) => return Some(reply),
+            Err(e) => return Some(format!("{}: sorry, {}", user.name, e)),
+        }
+    }
+
+    fn run(&self, discussion: &mut Discussion, user: &ChatUser, command: ChatCommand) -> Result<String, String> {
+
+        match command {
+            ChatCommand::Help => return Ok(HELP.to_string()),
+            ChatCommand::Queue => return Ok(format_queue(discussion)),
+            _ => (),
+        }
+
+        let token: String = self.join(discussion, user)?;
+
+        match command {
+            ChatCommand::NewPoint | ChatCommand::Response => {
+                let is_response: bool = command == ChatCommand::Response;
+                match discussion.add_speech_as(&token, None, is_response) {
+                    AddSpeechResult::Success => (),
+                    AddSpeechResult::NothingToRespondTo => return Err("there is nothing to respond to yet".to_string()),
+                    AddSpeechResult::TooManyQueuedSpeeches => return Err("you already have as many speeches waiting as you're allowed".to_string()),
+                    AddSpeechResult::DiscussionIsFrozen => return Err("this discussion has been closed".to_string()),
+                    result => {
+                        debug_panic!(format!("{:?}", result));
+                        return Err("that didn't work".to_string());
+                    }
+                }
+                // Nothing waiting means the speech went straight to the floor
+                let id: SpeechId = match (discussion.latest_waiting_speech_of(&token), discussion.current_speech()) {
+                    (Some(id), _) => id,
+                    (None, Some(current)) => return Ok(format!("{}: you're up now (#{})", user.name, current.0)),
+                    (None, None) => {
+                        debug_panic!();
+                        return Ok(format!("{}: you're on the list", user.name));
+                    }
+                };
+                let ahead: usize = discussion.speeches_in_order().iter()
+                    .filter(|(_, has_been_given)| !has_been_given)
+                    .take_while(|(waiting, _)| *waiting != id)
+                    .count();
+                return Ok(format!("{}: you're down as #{}, with {} ahead of you", user.name, id.0, ahead));
+            },
+            ChatCommand::Withdraw(id) => {
+                let id: SpeechId = match id.or_else(|| discussion.latest_waiting_speech_of(&token)) {
+                    Some(id) => id,
+                    None => return Err("you have nothing waiting to withdraw".to_string()),
+                };
+                match discussion.withdraw_speech_as(&token, id) {
+                    WithdrawSpeechResult::Success => return Ok(format!("{}: withdrew #{}", user.name, id.0)),
+                    WithdrawSpeechResult::NoSuchSpeech => return Err(format!("there is no speech #{}", id.0)),
+                    WithdrawSpeechResult::AlreadyGiven => return Err(format!("#{} has already been given", id.0)),
+                    WithdrawSpeechResult::OnlyChairCanWithdrawForOthers => return Err(format!("#{} isn't yours to withdraw", id.0)),
+                    WithdrawSpeechResult::DiscussionIsFrozen => return Err("this discussion has been closed".to_string()),
+                    WithdrawSpeechResult::NotJoined => {
+                        debug_panic!();
+                        return Err("that didn't work".to_string());
+                    }
+                }
+            },
+            // Anyone can raise a point of order, which stops the clock until a
+            //  chair deals with it
+            ChatCommand::PointOfOrder => {
+                if discussion.paused {
+                    return Err("a point of order is already being dealt with".to_string());
+                }
+                discussion.set_paused(true);
+                return Ok(format!("Point of order from {}. The speaking order is paused.", user.name));
+            },
+            ChatCommand::Resume => {
+                ChatBot::allowed(discussion, &token, Action::Pause)?;
+                discussion.set_paused(false);
+                return Ok(format!("Resuming. {}", format_queue(discussion)));
+            },
+            ChatCommand::Next => {
+                ChatBot::allowed(discussion, &token, Action::Next)?;
+                match discussion.goto_next_speech() {
+                    GotoSpeechResult::Success => return Ok(format_queue(discussion)),
+                    GotoSpeechResult::NoSpeechToGoTo => return Err("nobody is waiting to speak".to_string()),
+                    GotoSpeechResult::DiscussionIsFrozen => return Err("this discussion has been closed".to_string()),
+                    GotoSpeechResult::IllegalDiscussionSomehow => {
+                        debug_panic!();
+                        return Err("that didn't work".to_string());
+                    }
+                }
+            },
+            ChatCommand::Queue | ChatCommand::Help => {
+                debug_panic!();
+                return Err("that didn't work".to_string());
+            },
+        }
+    }
+
+}
+
+/// Runs `bot` over lines like `ada: !new`, writing each reply as a line of its
+///  own. The nick before the colon is used as both the user's ID and their name,
+///  and lines without one are skipped.
+pub fn run_lines(bot: &ChatBot, discussion: &Mutex<Discussion>, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    for line in input.lines() {
+        let line: String = line?;
+        let (nick, message) = match line.split_once(':') {
+            Some((nick, message)) if !nick.trim().is_empty() => (nick.trim(), message),
+            _ => continue,
+        };
+        let reply: Option<String> = match discussion.lock() {
+            Ok(mut discussion) => bot.handle(&mut discussion, &ChatUser { id: nick, name: nick }, message),
+            Err(e) => {
+                debug_panic!(e.to_string());
+                continue;
+            }
+        };
+        if let Some(reply) = reply {
+            writeln!(output, "{}", reply)?;
+            output.flush()?;
+        }
+    }
+    return Ok(());
+}
+
+#[test]
+fn test_commands_parse() {
+    assert_eq!(ChatCommand::parse("Stack me "), Some(Ok(ChatCommand::NewPoint)));
+    assert_eq!(ChatCommand::parse("!R"), Some(Ok(ChatCommand::Response)));
+    assert_eq!(ChatCommand::parse("!withdraw #3"), Some(Ok(ChatCommand::Withdraw(Some(SpeechId(3))))));
+    assert_eq!(ChatCommand::parse("!withdraw"), Some(Ok(ChatCommand::Withdraw(None))));
+    assert!(matches!(ChatCommand::parse("!withdraw mine"), Some(Err(_))));
+    assert_eq!(ChatCommand::parse("I think we should stack me later"), None);
+    assert_eq!(ChatCommand::parse("!weather"), None);
+}
+
+#[test]
+fn test_a_meeting_over_chat() {
+    use crate::discussion::DiscussionSettings;
+    let discussion = Discussion::new(DiscussionSettings::default());
+    let bot: ChatBot = ChatBot::new(["carol"]);
+    let script: &str = "\
+        ada: stack me\n\
+        alan: !response\n\
+        bea: good morning all\n\
+        bea: !new\n\
+        alan: !next\n\
+        bea: !withdraw\n\
+        bea: !new\n\
+        ada: !poo\n\
+        carol: !resume\n\
+        carol: !next\n\
+        alan: !withdraw 0\n\
+        carol: !withdraw 0\n";
+    let mut output: Vec<u8> = Vec::new();
+    run_lines(&bot, &discussion, script.as_bytes(), &mut output).unwrap();
+    let replies: Vec<String> = String::from_utf8(output).unwrap().lines().map(str::to_string).collect();
+    assert_eq!(replies, vec![
+        "ada: you're up now (#0)",
+        "alan: you're down as #1, with 0 ahead of you",
+        "bea: you're down as #2, with 1 ahead of you",
+        "alan: sorry, only the chair can do that",
+        "bea: withdrew #2",
+        "bea: you're down as #3, with 1 ahead of you",
+        "Point of order from ada. The speaking order is paused.",
+        "Resuming. Now: #0 ada (new point, 0:00). Then: #1 alan (response), #3 bea (new point).",
+        "Now: #1 alan (response, 0:00). Then: #3 bea (new point).",
+        "alan: sorry, #0 isn't yours to withdraw",
+        "carol: sorry, #0 has already been given",
+    ]);
+}
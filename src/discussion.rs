@@ -1,25 +1,47 @@
-mod linked_list_extra;
-
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use std::collections::{HashMap, LinkedList};
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, VecDeque};
 use std::mem;
 
+use chrono::prelude::*;
 use debug_panic::debug_panic;
+use serde::{Serialize, Deserialize};
 
-use self::speech::{Speaker, Speech};
+use self::speech::{Speaker, Speech, SpeechId, SpeechStore};
+use self::participant::{Action, Participant, Role};
+use self::roster::Roster;
+use self::series::{Series, SeriesTotals};
+use self::history::{History, Snapshot};
+use self::events::Observed;
+use self::motion::Motion;
 
 pub mod speech;
+pub mod participant;
+pub mod roster;
+pub mod series;
+mod history;
+pub mod events;
+pub mod portable;
+pub mod motion;
+#[cfg(test)]
+mod order_model;
 
 const ZERO_SECONDS: Duration = Duration::from_secs(0);
 const ONE_SECOND: Duration = Duration::from_secs(1);
 
-#[derive(Debug, PartialEq, Eq)]
+/// How upcoming speeches are ordered. New points always go after the current
+///  response block, and responses always stay behind the new point they
+///  respond to; the mode decides the order within each of those.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PriorityMode {
+    #[serde(alias = "fcfs")]
     FirstComeFirstServe,
+    #[serde(alias = "brevity")]
     FavourBriefest,
-    // FavourShiest,
+    /// Whoever has given the fewest speeches goes first
+    #[serde(alias = "shy")]
+    FavourShiest,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -27,44 +49,194 @@ pub enum GotoSpeechResult {
     Success,
     NoSpeechToGoTo,
     IllegalDiscussionSomehow,
+    DiscussionIsFrozen,
 }
 
-type ResponseBlock = LinkedList<Box<Speech>>;
-type NewPointThenResponseBlock = (Box<Speech>, ResponseBlock);
-type ListOfSpeeches = LinkedList<NewPointThenResponseBlock>;
+#[derive(Debug, PartialEq, Eq)]
+pub enum AddSpeechResult {
+    Success,
+    EmptyName,
+    NothingToRespondTo,
+    TooManyQueuedSpeeches,
+    NotJoined,
+    OnlyChairCanAddForOthers,
+    DiscussionIsFrozen,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SplitSpeakerResult {
+    Success,
+    UnknownName,
+    NotAnAlias,
+    NoSuchSpeech(SpeechId),
+    SpeechNotBySpeaker(SpeechId),
+    DiscussionIsFrozen,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WithdrawSpeechResult {
+    Success,
+    NoSuchSpeech,
+    AlreadyGiven,
+    NotJoined,
+    OnlyChairCanWithdrawForOthers,
+    DiscussionIsFrozen,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum UndoResult {
+    Success,
+    NothingToUndo,
+    NothingToRedo,
+    DiscussionIsFrozen,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AssignRoleResult {
+    Success,
+    NotJoined,
+    LastChair,
+    DiscussionIsFrozen,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum JoinResult {
+    Success,
+    AlreadyJoined,
+    NameTaken,
+    DiscussionIsFrozen,
+}
 
+/// A discussion starts out `Open`. Once it is `Closed` or `Archived` it is frozen:
+///  the speaking order can still be read, but every mutating method becomes a
+///  no-op and the clock stops. The only difference between the two is that an
+///  archived discussion has been written to storage and is about to be evicted
+///  from memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lifecycle {
+    Open,
+    Closed,
+    Archived,
+}
+
+/// The settings a discussion is created with. Time limits are advisory; going
+///  over one is shown in the speaking order but never cuts anyone off.
+///
+/// A discussion which is one of a `series` starts with the series' roster as
+///  well as `roster`. `rank_by_series` has the non-FCFS priority modes count
+///  what each speaker did in the series' earlier meetings too.
+#[derive(Debug, Clone)]
+pub struct DiscussionSettings {
+    pub priority_mode: PriorityMode,
+    pub new_point_time_limit: Option<Duration>,
+    pub response_time_limit: Option<Duration>,
+    pub max_queued_speeches_per_speaker: Option<usize>,
+    pub roster: Roster,
+    pub series: Option<Series>,
+    pub rank_by_series: bool,
+}
+
+impl Default for DiscussionSettings {
+    fn default() -> Self {
+        DiscussionSettings {
+            priority_mode: PriorityMode::FirstComeFirstServe,
+            new_point_time_limit: None,
+            response_time_limit: None,
+            max_queued_speeches_per_speaker: None,
+            roster: Roster::default(),
+            series: None,
+            rank_by_series: false,
+        }
+    }
+}
+
+// The speaking order only holds IDs; the speeches themselves live in
+//  `Discussion::speeches`
+type ResponseBlock = VecDeque<SpeechId>;
+type NewPointThenResponseBlock = (SpeechId, ResponseBlock);
+type ListOfSpeeches = VecDeque<NewPointThenResponseBlock>;
+
+/// A speaking order and everything needed to keep it: who has spoken, who is
+///  waiting, the participants and their roles, and the undo history. Create one
+///  with `Discussion::new`, which also starts its clock.
 #[derive(Debug)]
 pub struct Discussion {
     pub speakers: HashMap<String, Arc<Mutex<Speaker>>>, 
-    pub current_new_point: Option<Box<Speech>>,
+    pub participants: HashMap<String, Participant>, // Keyed by session token
+    pub roster: Roster,
+    /// The series this discussion is one of, as it stood when the discussion
+    ///  joined it
+    pub series: Option<Series>,
+    pub speeches: SpeechStore,
+    pub current_new_point: Option<SpeechId>,
     pub first_response_block: ResponseBlock, // An empty response block represents a paused discussion
     pub upcoming_speeches: ListOfSpeeches,
     pub past_speeches: ListOfSpeeches,
     pub duration: Duration,
     pub paused: bool,
     pub as_html: String,
+    pub lifecycle: Lifecycle,
+    /// Lets anyone who has it open the read-only projector view
+    pub display_token: Option<String>,
+    pub last_activity: Instant,
+    pub new_point_time_limit: Option<Duration>,
+    pub response_time_limit: Option<Duration>,
+    pub max_queued_speeches_per_speaker: Option<usize>,
+    pub motions: Vec<Motion>,
     priority_mode: PriorityMode,
+    rank_by_series: bool,
+    // The ID the next speech will be given (see `SpeechId`)
+    next_speech_id: usize,
+    // Kept outside the snapshots, along with `next_speech_id`, so that undoing
+    //  never hands out an ID twice
+    history: History,
+    // What `take_events` last saw
+    observed: Observed,
+    // Set when the speakers' totals have been counted again, or put back by
+    //  undoing, without the speaking order being sorted since
+    order_is_stale: bool,
 }
 
 impl Discussion {
     
-    pub fn new() -> Arc<Mutex<Self>> {
-
-        let ret: Arc<Mutex<Self>> = Arc::new (
-            Mutex::new (
-                Self {
-                    speakers: HashMap::new(),
-                    current_new_point: None,
-                    first_response_block: LinkedList::new(),
-                    upcoming_speeches: LinkedList::new(),
-                    past_speeches: LinkedList::new(),
-                    duration: ZERO_SECONDS,
-                    paused: false,
-                    as_html: "".to_string(),
-                    priority_mode: PriorityMode::FirstComeFirstServe,
-                }
-            )
-        );
+    pub fn new(settings: DiscussionSettings) -> Arc<Mutex<Self>> {
+
+        let mut discussion: Self = Self {
+            speakers: HashMap::new(),
+            participants: HashMap::new(),
+            roster: Roster::default(),
+            series: None,
+            speeches: SpeechStore::default(),
+            current_new_point: None,
+            first_response_block: VecDeque::new(),
+            upcoming_speeches: VecDeque::new(),
+            past_speeches: VecDeque::new(),
+            duration: ZERO_SECONDS,
+            paused: false,
+            as_html: "".to_string(),
+            lifecycle: Lifecycle::Open,
+            display_token: None,
+            last_activity: Instant::now(),
+            new_point_time_limit: settings.new_point_time_limit,
+            response_time_limit: settings.response_time_limit,
+            max_queued_speeches_per_speaker: settings.max_queued_speeches_per_speaker,
+            motions: Vec::new(),
+            priority_mode: settings.priority_mode,
+            rank_by_series: settings.rank_by_series,
+            next_speech_id: 0,
+            history: History::default(),
+            observed: Observed::default(),
+            order_is_stale: false,
+        };
+
+        // The roster a discussion starts with isn't something to undo
+        discussion.load_roster(settings.roster);
+        if let Some(series) = settings.series {
+            discussion.join_series(series);
+        }
+        discussion.history.clear();
+
+        let ret: Arc<Mutex<Self>> = Arc::new(Mutex::new(discussion));
 
         let ret_clock_pointer: Arc<Mutex<Self>> = Arc::clone(&ret);
 
@@ -76,10 +248,12 @@ impl Discussion {
                 
                 match (*ret_clock_pointer).lock() {
                     Ok(mut ret_locked) => {
-                        ret_locked.tick_clock();
-                        if ret_locked.priority_mode != PriorityMode::FirstComeFirstServe {
-                            ret_locked.resort_speaking_order();
+                        // Nothing in a frozen discussion can change anymore, so
+                        //  the clock has no more work to do
+                        if ret_locked.is_frozen() {
+                            break;
                         }
+                        ret_locked.tick_clock();
                     },
                     Err(e) => debug_panic!(e.to_string()),
                 }
@@ -92,144 +266,332 @@ impl Discussion {
 
     }
 
-    // TODO - Fix this method. It doesn't sort speakers by brevity, even when set
-    //  to that mode. (New points work fine though)
-    fn sort_response_block(block: &mut ResponseBlock, is_more_pressing: fn(&Box<Speech>, &Box<Speech>) -> bool) {
-
-        // We will create a temporary variable to store the items from `block` after
-        //  we've looked at each of them. This will eventually become `block` itself
-        let mut tmp1: ResponseBlock = LinkedList::new();
-        loop {
-            match block.pop_front() {
-                Some(response) => {
-                    let mut tmp2: ResponseBlock = LinkedList::new();
-                    loop {
-                        match tmp1.pop_back() {
-                            Some(tmp1_back) => {
-                                if is_more_pressing(&response, &tmp1_back) {
-                                    tmp2.push_front(tmp1_back);
-                                } else {
-                                    tmp1.push_back(tmp1_back);
-                                    break;
-                                }
-                            },
-                            None => break,
-                        }
-                    }
-                    tmp1.push_back(response);
-                    tmp1.append(&mut tmp2);
-                    debug_assert!(tmp2.is_empty());
-                    drop(tmp2);
-                }
-                None => {
-                    block.append(&mut tmp1);
-                    debug_assert!(tmp1.is_empty());
-                    return;
-                },
-            }
+    pub fn is_frozen(&self) -> bool {
+        return self.lifecycle != Lifecycle::Open;
+    }
+
+    /// Closing a discussion freezes it in place. It stays in memory (and can
+    ///  still be viewed) until it is archived or expires
+    pub fn close(&mut self) {
+        if self.lifecycle == Lifecycle::Open {
+            self.lifecycle = Lifecycle::Closed;
+            self.touch();
         }
     }
-    
-    pub fn resort_speaking_order(&mut self) {
 
-        // First we define a closure which compares two speeches `a` and `b` and
-        //  returns true if `a` should be placed before `b`.
-        let is_more_pressing: fn(&Box<Speech>, &Box<Speech>) -> bool = match self.priority_mode {
+    pub fn mark_archived(&mut self) {
+        self.lifecycle = Lifecycle::Archived;
+    }
 
-            PriorityMode::FirstComeFirstServe => |a: &Box<Speech>, b: &Box<Speech>| -> bool {
-                return a.fcfs_order < b.fcfs_order;
-            },
+    /// Returns how long it has been since anyone last changed the discussion.
+    ///  Ticks of the clock do not count as activity
+    pub fn idle_for(&self) -> Duration {
+        return self.last_activity.elapsed();
+    }
 
-            PriorityMode::FavourBriefest => |a: &Box<Speech>, b: &Box<Speech>| -> bool {
+    fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
 
-                let a_total_speaking_time: Duration = match a.speaker.lock() {
-                    Ok(speaker) => speaker.total_speaking_time,
-                    Err(e) => {
-                        debug_panic!(e.to_string());
-                        return false;
-                    }
-                };
+    // Called just before every change the chair might want to undo
+    fn remember(&mut self) {
+        let snapshot: Snapshot = Snapshot::take(self);
+        self.history.record(snapshot);
+    }
 
-                let b_total_speaking_time: Duration = match b.speaker.lock() {
-                    Ok(speaker) => speaker.total_speaking_time,
-                    Err(e) => {
-                        debug_panic!(e.to_string());
-                        return false;
-                    }
-                };
+    pub fn can_undo(&self) -> bool {
+        return !self.is_frozen() && self.history.can_undo();
+    }
 
-                return a_total_speaking_time < b_total_speaking_time;
+    pub fn can_redo(&self) -> bool {
+        return !self.is_frozen() && self.history.can_redo();
+    }
 
-            },
+    /// Puts back the speaking order, speakers and settings exactly as they were
+    ///  before the last change. Time spent speaking since then goes with it
+    pub fn undo(&mut self) -> UndoResult {
+        if self.is_frozen() {
+            return UndoResult::DiscussionIsFrozen;
+        }
+        if !self.history.can_undo() {
+            return UndoResult::NothingToUndo;
+        }
+        let current: Snapshot = Snapshot::take(self);
+        match self.history.undo(current) {
+            Some(previous) => previous.restore(self),
+            None => debug_panic!(),
+        }
+        self.order_is_stale = true;
+        self.touch();
+        return UndoResult::Success;
+    }
+
+    pub fn redo(&mut self) -> UndoResult {
+        if self.is_frozen() {
+            return UndoResult::DiscussionIsFrozen;
+        }
+        if !self.history.can_redo() {
+            return UndoResult::NothingToRedo;
+        }
+        let current: Snapshot = Snapshot::take(self);
+        match self.history.redo(current) {
+            Some(next) => next.restore(self),
+            None => debug_panic!(),
+        }
+        self.order_is_stale = true;
+        self.touch();
+        return UndoResult::Success;
+    }
 
+    // What the speaking order is sorted by in the current priority mode.
+    //  Speeches with lower keys go first, and ties keep their current order
+    fn sort_key(&self, id: SpeechId) -> (Duration, usize) {
+        let speech: &Speech = match self.speeches.get(id) {
+            Some(speech) => speech,
+            None => {
+                debug_panic!();
+                return (ZERO_SECONDS, 0);
+            }
         };
+        match self.priority_mode {
+            PriorityMode::FirstComeFirstServe => return (ZERO_SECONDS, speech.id.0),
+            PriorityMode::FavourBriefest => match speech.speaker.lock() {
+                Ok(speaker) => return (speaker.total_speaking_time + Duration::from_secs(self.earlier_totals_of(&speaker).speaking_time), 0),
+                Err(e) => {
+                    debug_panic!(e.to_string());
+                    return (ZERO_SECONDS, 0);
+                }
+            },
+            PriorityMode::FavourShiest => match speech.speaker.lock() {
+                Ok(speaker) => return (ZERO_SECONDS, speaker.number_of_speeches_given as usize + self.earlier_totals_of(&speaker).speeches as usize),
+                Err(e) => {
+                    debug_panic!(e.to_string());
+                    return (ZERO_SECONDS, 0);
+                }
+            },
+        }
+    }
 
-        // Now we move onto the meat of the function
-        
-        // We will go one by one and move the entire list of upcoming speeches
-        //  into `tmp1`, sorting them as we go. Note that tmp1 wi
-        let mut tmp1: ListOfSpeeches = LinkedList::new();
-        loop {
-            match self.upcoming_speeches.pop_front() {
-                Some((new_point, mut response_block)) => {
-
-                    // TODO make the sorting of the block and the finding location
-                    //  to insert the pair into two seperate threads
-
-                    // Thread 1
-                    {
-                        Self::sort_response_block(&mut response_block, is_more_pressing);
-                    }
+    // What the speaker did in the series' earlier meetings, if that is to count
+    //  towards the speaking order
+    fn earlier_totals_of(&self, speaker: &Speaker) -> SeriesTotals {
+        match (&self.series, self.rank_by_series) {
+            (Some(series), true) => return series.totals_of(speaker),
+            _ => return SeriesTotals::default(),
+        }
+    }
 
-                    // Thread 2
-                    let mut tmp2: ListOfSpeeches = LinkedList::new();
-                    loop {
+    /// Sorts the upcoming new points, and the responses queued behind each of
+    ///  them. The current block is left alone, since its responses are already
+    ///  under way. Each key is worked out once rather than on every comparison.
+    ///  The clock only does this when something has changed the totals since
+    ///  the last time; otherwise a tick just moves the current speaker's
+    ///  speeches (see `reposition_speeches_of`).
+    pub fn resort_speaking_order(&mut self) {
+        let mut upcoming_speeches: Vec<NewPointThenResponseBlock> = Vec::from(mem::take(&mut self.upcoming_speeches));
+        for (_, response_block) in &mut upcoming_speeches {
+            response_block.make_contiguous().sort_by_cached_key(|id| self.sort_key(*id));
+        }
+        upcoming_speeches.sort_by_cached_key(|(new_point, _)| self.sort_key(*new_point));
+        self.upcoming_speeches = ListOfSpeeches::from(upcoming_speeches);
+        self.order_is_stale = false;
+    }
 
-                        match tmp1.pop_back() {
+    // Moves the speaker's upcoming new points, and their responses within each
+    //  block, to where they go now that their key has gone up. Everything else
+    //  is still in order, so each lot is taken out and put back in front of the
+    //  first speech whose key isn't lower, just where a stable sort would leave
+    //  them. Finding their speeches is still a walk along the speaking order,
+    //  but no speaker is locked on the way, and besides their own key only the
+    //  O(log n) keys the binary searches look at are worked out.
+    fn reposition_speeches_of(&mut self, speaker: &Arc<Mutex<Speaker>>) {
 
-                            Some(tmp1_back) => {
-                                if is_more_pressing(&new_point, &tmp1_back.0) {
-                                    tmp2.push_front(tmp1_back);
-                                } else {
-                                    tmp1.push_back(tmp1_back);
-                                    break;
-                                }
-                            },
+        let is_theirs = |id: &SpeechId| self.speeches.get(*id).is_some_and(|speech| Arc::ptr_eq(&speech.speaker, speaker));
+        let mut upcoming_speeches: ListOfSpeeches = mem::take(&mut self.upcoming_speeches);
 
-                            None => break,
-                        }
+        for (_, response_block) in &mut upcoming_speeches {
+            if !response_block.iter().any(is_theirs) {
+                continue;
+            }
+            let (theirs, mut others): (ResponseBlock, ResponseBlock) = response_block.drain(..).partition(is_theirs);
+            let key: (Duration, usize) = self.sort_key(theirs[0]);
+            let rest: ResponseBlock = others.split_off(others.partition_point(|id| self.sort_key(*id) < key));
+            others.extend(theirs);
+            others.extend(rest);
+            *response_block = others;
+        }
 
-                    }
+        if upcoming_speeches.iter().any(|(new_point, _)| is_theirs(new_point)) {
+            let (theirs, mut others): (ListOfSpeeches, ListOfSpeeches) = upcoming_speeches.into_iter().partition(|(new_point, _)| is_theirs(new_point));
+            let key: (Duration, usize) = self.sort_key(theirs[0].0);
+            let rest: ListOfSpeeches = others.split_off(others.partition_point(|(new_point, _)| self.sort_key(*new_point) < key));
+            others.extend(theirs);
+            others.extend(rest);
+            upcoming_speeches = others;
+        }
 
-                    tmp1.push_back((new_point, response_block));
-                    tmp1.append(&mut tmp2);
-                    debug_assert!(tmp2.is_empty());
-                    drop(tmp2);
+        self.upcoming_speeches = upcoming_speeches;
+    }
 
-                },
+    pub fn get_priority_mode(&self) -> &PriorityMode {
+        return &self.priority_mode;
+    }
 
-                None => {
-                    self.upcoming_speeches.append(&mut tmp1);
-                    debug_assert!(tmp1.is_empty());
-                    return;
-                },
+    pub fn set_priority_mode(&mut self, mode: PriorityMode) {
+        if self.is_frozen() {
+            return;
+        }
+        self.remember();
+        self.priority_mode = mode;
+        self.resort_speaking_order();
+        self.touch();
+    }
 
-            }
+    pub fn ranks_by_series(&self) -> bool {
+        return self.rank_by_series;
+    }
+
+    /// Has the non-FCFS priority modes count the series' earlier meetings as
+    ///  well as this one. Makes no difference outside of a series
+    pub fn set_rank_by_series(&mut self, rank_by_series: bool) {
+        if self.is_frozen() {
+            return;
         }
+        self.remember();
+        self.rank_by_series = rank_by_series;
+        self.resort_speaking_order();
+        self.touch();
+    }
 
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.is_frozen() {
+            return;
+        }
+        self.remember();
+        self.paused = paused;
+        self.touch();
     }
 
-    pub fn set_priority_mode(&mut self, mode: PriorityMode) {
-        self.priority_mode = mode;
+    /// Sets up a speaker for everyone on the roster, already aliased to each of
+    ///  their alternative names. Returns false if the discussion is frozen
+    pub fn load_roster(&mut self, roster: Roster) -> bool {
+
+        if self.is_frozen() {
+            return false;
+        }
+
+        self.remember();
+        self.apply_roster(roster);
+        self.touch();
+        return true;
+    }
+
+    fn apply_roster(&mut self, roster: Roster) {
+        for entry in &roster.entries {
+            if !self.speakers.contains_key(&entry.name) {
+                self.speakers.insert(entry.name.clone(), Arc::new(Mutex::new(Speaker::new(entry.name.clone()))));
+            }
+            for alias in &entry.aliases {
+                self.merge_speakers(&entry.name, alias);
+            }
+        }
+        self.roster = roster;
+    }
+
+    /// Makes this discussion one of `series`, adding the series' roster to its
+    ///  own. Returns false if the discussion is frozen
+    pub fn join_series(&mut self, series: Series) -> bool {
+
+        if self.is_frozen() {
+            return false;
+        }
+
+        self.remember();
+        let mut roster: Roster = self.roster.clone();
+        roster.merge(series.roster.clone());
+        self.apply_roster(roster);
+        self.series = Some(series);
         self.resort_speaking_order();
+        self.touch();
+        return true;
+    }
+
+    // Works out which speaker a typed name refers to. Names we have already
+    //  seen are taken as they are; otherwise the roster gets a chance to match
+    //  the name up with a known member, allowing for case and small typos
+    fn resolve_name(&self, typed: String) -> String {
+        if self.speakers.contains_key(&typed) {
+            return typed;
+        }
+        match self.roster.find(&typed) {
+            Some(entry) => return entry.name.clone(),
+            None => return typed,
+        }
+    }
+
+    /// The last speech still waiting to be given under the name the holder of
+    ///  `token` joined with
+    pub fn latest_waiting_speech_of(&self, token: &str) -> Option<SpeechId> {
+        let name: String = self.participants.get(token)?.name.clone()?;
+        return self.speeches_in_order().into_iter().rev()
+            .filter(|(_, has_been_given)| !has_been_given)
+            .map(|(id, _)| id)
+            .find(|id| match self.speeches.get(*id).map(|speech| speech.speaker.lock()) {
+                Some(Ok(speaker)) => self.names_match(&speaker.name, &name),
+                Some(Err(e)) => {
+                    debug_panic!(e.to_string());
+                    false
+                },
+                None => false,
+            });
+    }
+
+    pub fn is_chair(&self, token: &str) -> bool {
+        return self.participants.get(token).is_some_and(Participant::is_chair);
+    }
+
+    pub fn role_of(&self, token: &str) -> Option<Role> {
+        return self.participants.get(token).map(|participant| participant.role);
+    }
+
+    /// Gives whoever joined as `name` a new role. The last chair can't step
+    ///  down, since then nobody could hand out roles any more
+    pub fn assign_role(&mut self, name: &str, role: Role) -> AssignRoleResult {
+
+        if self.is_frozen() {
+            return AssignRoleResult::DiscussionIsFrozen;
+        }
+
+        let token: String = match self.participants.iter().find(|(_, participant)| participant.name.as_ref().is_some_and(|own_name| self.names_match(name, own_name))) {
+            Some((token, _)) => token.clone(),
+            None => return AssignRoleResult::NotJoined,
+        };
+
+        let n_chairs: usize = self.participants.values().filter(|participant| participant.is_chair()).count();
+        if self.is_chair(&token) && role != Role::Chair && n_chairs == 1 {
+            return AssignRoleResult::LastChair;
+        }
+
+        self.touch();
+        if let Some(participant) = self.participants.get_mut(&token) {
+            participant.role = role;
+        }
+        return AssignRoleResult::Success;
     }
 
     pub fn alias_speakers(&mut self, name1: &String, name2: &String) {
 
-        if name1 == "" || name2 == "" {
+        if self.is_frozen() || name1.is_empty() || name2.is_empty() {
             return;
         }
 
+        self.remember();
+        self.touch();
+        self.merge_speakers(name1, name2);
+    }
+
+    fn merge_speakers(&mut self, name1: &String, name2: &String) {
+
         // First we try and find speakers with name1 and name2. If either of them 
         //  don't exist, then we create it
         let [speaker1_arc, speaker2_arc]: [Arc<Mutex<Speaker>>; 2] = [name1, name2].map(|name: &String| self.speakers.get(name).map(Arc::clone).unwrap_or_else(||Arc::new(Mutex::new(Speaker::new((&name).to_string())))));
@@ -239,42 +601,31 @@ impl Discussion {
         //  are already trivially aliased
         if !Arc::ptr_eq(&speaker1_arc, &speaker2_arc) {
 
+            // If speaker1 was only just created it needs to be findable under
+            //  its own name too, or the next speech under name1 would create
+            //  yet another speaker
+            if !self.speakers.contains_key(name1) {
+                self.speakers.insert(name1.clone(), Arc::clone(&speaker1_arc));
+            }
+
             // We then insert a reference to speaker1 anywhere that speaker2 was 
             //  (or should have been)
             match speaker2_arc.lock() {
                 Ok(speaker2) => {
                     self.speakers.insert(String::clone(&speaker2.name), Arc::clone(&speaker1_arc));
                     for name in &speaker2.aliases {
-                        self.speakers.insert(String::clone(&name), Arc::clone(&speaker1_arc));
+                        self.speakers.insert(String::clone(name), Arc::clone(&speaker1_arc));
                     }
                 },
                 Err(e) => debug_panic!(e.to_string()),
             };
     
-            // This next block will now go through each speech in the past,
-            //  present, and future speaking order, replacing any instance of
-            //  speaker2 with one to speaker1
-            {
-    
-                let speaker1_to_speaker2 = |speech: &mut Speech| if Arc::ptr_eq(&speech.speaker, &speaker2_arc) {
+            // Every speech by speaker2, wherever it is in the speaking order,
+            //  now becomes a speech by speaker1
+            for speech in self.speeches.iter_mut() {
+                if Arc::ptr_eq(&speech.speaker, &speaker2_arc) {
                     speech.speaker = Arc::clone(&speaker1_arc);
-                };
-    
-                if let Some(speech) = &mut self.current_new_point {
-                    speaker1_to_speaker2(speech);
-                }
-    
-                for speech in &mut self.first_response_block {
-                    speaker1_to_speaker2(speech);
-                }
-                
-                for (new_point, response_block) in Iterator::chain(self.past_speeches.iter_mut(), self.upcoming_speeches.iter_mut()) {
-                    speaker1_to_speaker2(new_point);
-                    for response in response_block {
-                        speaker1_to_speaker2(response);
-                    }
                 }
-    
             }
     
             // Finally, we should have scrubbed all references to speaker2 from
@@ -286,58 +637,426 @@ impl Discussion {
                 debug_panic!();
             }
 
+            self.recount_speakers();
+
         }
 
     }
 
-    pub fn add_new_speech(&mut self, speaker_name: String, is_response: bool) {
+    /// Every speech in the discussion in speaking order: past speeches, then the
+    ///  current one, then everything still to come. The flag is true for
+    ///  speeches which have been given or are being given right now.
+    pub fn speeches_in_order(&self) -> Vec<(SpeechId, bool)> {
+        let mut speeches: Vec<(SpeechId, bool)> = Vec::new();
+        for (new_point, response_block) in &self.past_speeches {
+            speeches.push((*new_point, true));
+            speeches.extend(response_block.iter().map(|response| (*response, true)));
+        }
+        if let Some(current_new_point) = self.current_new_point {
+            speeches.push((current_new_point, true));
+        }
+        for (i, response) in self.first_response_block.iter().enumerate() {
+            speeches.push((*response, i == 0 && self.current_new_point.is_none()));
+        }
+        for (new_point, response_block) in &self.upcoming_speeches {
+            speeches.push((*new_point, false));
+            speeches.extend(response_block.iter().map(|response| (*response, false)));
+        }
+        return speeches;
+    }
 
-        if speaker_name == "" {
-            return;
+    /// The speech being given right now, if there is one
+    pub fn current_speech(&self) -> Option<SpeechId> {
+        return self.current_new_point.or(self.first_response_block.front().copied());
+    }
+
+    // Adds up every speaker's speeches. Speakers are keyed by the address of
+    //  their `Arc`, since every name a speaker goes by shares the one `Arc`
+    fn tally_speakers(&self) -> HashMap<*const Mutex<Speaker>, (Duration, u16)> {
+        let mut totals: HashMap<*const Mutex<Speaker>, (Duration, u16)> = self.speakers.values()
+            .map(|speaker_arc| (Arc::as_ptr(speaker_arc), (ZERO_SECONDS, 0)))
+            .collect();
+        for (id, has_been_given) in self.speeches_in_order() {
+            let speech: &Speech = match self.speeches.get(id) {
+                Some(speech) => speech,
+                None => {
+                    debug_panic!();
+                    continue;
+                }
+            };
+            let (total_speaking_time, number_of_speeches_given) = totals.entry(Arc::as_ptr(&speech.speaker)).or_insert((ZERO_SECONDS, 0));
+            *total_speaking_time += speech.duration;
+            if has_been_given {
+                *number_of_speeches_given += 1;
+            }
+        }
+        return totals;
+    }
+
+    // Refreshes every speaker's cached totals from their speeches. Apart from
+    //  the clock adding each second to whoever is speaking, this is the only
+    //  place the totals are written, so they can't drift away from the speeches
+    //  whatever happens to the speaking order. The upcoming speeches are sorted
+    //  by those totals, so the clock is left to put them back in order.
+    fn recount_speakers(&mut self) {
+        let totals = self.tally_speakers();
+        for speaker_arc in self.speakers.values() {
+            let (total_speaking_time, number_of_speeches_given) = totals[&Arc::as_ptr(speaker_arc)];
+            match speaker_arc.lock() {
+                Ok(mut speaker) => {
+                    speaker.total_speaking_time = total_speaking_time;
+                    speaker.number_of_speeches_given = number_of_speeches_given;
+                },
+                Err(e) => debug_panic!(e.to_string()),
+            }
+        }
+        self.order_is_stale = true;
+    }
+
+    /// Checks that every speaker's cached totals are what their speeches add up
+    ///  to, and that nobody has speeches without being a known speaker
+    pub fn check_speaker_totals(&self) -> Result<(), String> {
+        let totals = self.tally_speakers();
+        for (id, _) in self.speeches_in_order() {
+            let speech: &Speech = match self.speeches.get(id) {
+                Some(speech) => speech,
+                None => return Err(format!("speech {:?} is in the speaking order but not in the discussion", id)),
+            };
+            if !self.speakers.values().any(|speaker_arc| Arc::ptr_eq(speaker_arc, &speech.speaker)) {
+                return Err(format!("a speech added under {:?} belongs to a speaker who isn't in the discussion", speech.given_name));
+            }
+        }
+        for speaker_arc in self.speakers.values() {
+            let (total_speaking_time, number_of_speeches_given) = totals[&Arc::as_ptr(speaker_arc)];
+            match speaker_arc.lock() {
+                Ok(speaker) => if speaker.total_speaking_time != total_speaking_time || speaker.number_of_speeches_given != number_of_speeches_given {
+                    return Err(format!(
+                        "{:?} has {:?} over {} speeches cached, but their speeches add up to {:?} over {}",
+                        speaker.name, speaker.total_speaking_time, speaker.number_of_speeches_given, total_speaking_time, number_of_speeches_given,
+                    ));
+                },
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        return Ok(());
+    }
+
+    /// Undoes an alias by giving `name` its own speaker again. The speeches at
+    ///  given IDs go with it; if no speeches are picked, the ones which were
+    ///  added under `name` go. Both speakers'
+    ///  totals are then recounted from the speeches they are left with.
+    pub fn split_speaker(&mut self, name: &String, speech_ids: Option<Vec<SpeechId>>) -> SplitSpeakerResult {
+
+        if self.is_frozen() {
+            return SplitSpeakerResult::DiscussionIsFrozen;
         }
 
+        let old_speaker_arc: Arc<Mutex<Speaker>> = match self.speakers.get(name) {
+            Some(speaker_arc) => Arc::clone(speaker_arc),
+            None => return SplitSpeakerResult::UnknownName,
+        };
+
+        // A speaker can't be split off from itself, so `name` needs to be one of
+        //  the speaker's other names
+        match old_speaker_arc.lock() {
+            Ok(old_speaker) => if old_speaker.name == *name || !old_speaker.aliases.contains(name) {
+                return SplitSpeakerResult::NotAnAlias;
+            },
+            Err(e) => {
+                debug_panic!(e.to_string());
+                return SplitSpeakerResult::NotAnAlias;
+            },
+        }
+
+        let speech_ids: Vec<SpeechId> = match speech_ids {
+            Some(speech_ids) => {
+                for id in &speech_ids {
+                    match self.speeches.get(*id) {
+                        Some(speech) => if !Arc::ptr_eq(&speech.speaker, &old_speaker_arc) {
+                            return SplitSpeakerResult::SpeechNotBySpeaker(*id);
+                        },
+                        None => return SplitSpeakerResult::NoSuchSpeech(*id),
+                    }
+                }
+                speech_ids
+            },
+            None => self.speeches_in_order().into_iter()
+                .map(|(id, _)| id)
+                .filter(|id| self.speeches.get(*id).is_some_and(|speech| Arc::ptr_eq(&speech.speaker, &old_speaker_arc) && speech.given_name == *name))
+                .collect(),
+        };
+
+        self.remember();
+        self.touch();
+
+        match old_speaker_arc.lock() {
+            Ok(mut old_speaker) => {
+                old_speaker.aliases.remove(name);
+            },
+            Err(e) => debug_panic!(e.to_string()),
+        }
+        let new_speaker_arc: Arc<Mutex<Speaker>> = Arc::new(Mutex::new(Speaker::new(name.clone())));
+        self.speakers.insert(name.clone(), Arc::clone(&new_speaker_arc));
+
+        for id in speech_ids {
+            if let Some(speech) = self.speeches.get_mut(id) {
+                speech.speaker = Arc::clone(&new_speaker_arc);
+                speech.given_name = name.clone();
+            }
+        }
+
+        self.recount_speakers();
+
+        if self.priority_mode != PriorityMode::FirstComeFirstServe {
+            self.resort_speaking_order();
+        }
+
+        return SplitSpeakerResult::Success;
+    }
+
+    /// Takes a speech out of the speaking order before it has been given.
+    ///  Withdrawing a new point takes its responses with it, since there would
+    ///  be nothing left for them to respond to
+    pub fn withdraw_speech(&mut self, id: SpeechId) -> WithdrawSpeechResult {
+
+        if self.is_frozen() {
+            return WithdrawSpeechResult::DiscussionIsFrozen;
+        }
+
+        if self.speeches.get(id).is_none() {
+            return WithdrawSpeechResult::NoSuchSpeech;
+        }
+
+        // The speech is only known to be withdrawable once it has been taken
+        //  out of the speaking order, so the snapshot has to come first
+        let before: Snapshot = Snapshot::take(self);
+
+        let mut withdrawn: Vec<SpeechId> = Vec::new();
+        let current_speech: Option<SpeechId> = self.current_speech();
+        if let Some(i) = self.first_response_block.iter().position(|response| *response == id && Some(id) != current_speech) {
+            self.first_response_block.remove(i);
+            withdrawn.push(id);
+        } else if let Some(i) = self.upcoming_speeches.iter().position(|(new_point, _)| *new_point == id) {
+            if let Some((new_point, response_block)) = self.upcoming_speeches.remove(i) {
+                withdrawn.push(new_point);
+                withdrawn.extend(response_block);
+            }
+        } else {
+            for (_, response_block) in &mut self.upcoming_speeches {
+                if let Some(i) = response_block.iter().position(|response| *response == id) {
+                    response_block.remove(i);
+                    withdrawn.push(id);
+                    break;
+                }
+            }
+        }
+
+        // Anything else has already been given or is being given right now
+        if withdrawn.is_empty() {
+            return WithdrawSpeechResult::AlreadyGiven;
+        }
+
+        self.history.record(before);
+        self.touch();
+        for id in withdrawn {
+            self.speeches.remove(id);
+        }
+        self.recount_speakers();
+        return WithdrawSpeechResult::Success;
+    }
+
+    /// Participants can only withdraw their own speeches; the chair can withdraw
+    ///  anybody's
+    pub fn withdraw_speech_as(&mut self, token: &str, id: SpeechId) -> WithdrawSpeechResult {
+
+        let participant: Participant = match self.participants.get(token) {
+            Some(participant) => participant.clone(),
+            None => return WithdrawSpeechResult::NotJoined,
+        };
+
+        let speaker: Arc<Mutex<Speaker>> = match self.speeches.get(id) {
+            Some(speech) => Arc::clone(&speech.speaker),
+            None => return WithdrawSpeechResult::NoSuchSpeech,
+        };
+
+        let is_own: bool = participant.name.is_some_and(|name| self.speakers.get(&name).is_some_and(|own| Arc::ptr_eq(own, &speaker)));
+        if is_own || participant.role.can(Action::WithdrawForOthers) {
+            return self.withdraw_speech(id);
+        } else {
+            return WithdrawSpeechResult::OnlyChairCanWithdrawForOthers;
+        }
+
+    }
+
+    // Counts the speeches by `speaker` which are still waiting to be given. The
+    //  speech being given right now doesn't count
+    fn count_queued_speeches(&self, speaker: &Arc<Mutex<Speaker>>) -> usize {
+        let is_by_speaker = |id: &&SpeechId| self.speeches.get(**id).is_some_and(|speech| Arc::ptr_eq(&speech.speaker, speaker));
+        let n_current_responses: usize = if self.current_new_point.is_some() { 0 } else { 1 };
+        return self.first_response_block.iter().skip(n_current_responses).filter(is_by_speaker).count()
+            + self.upcoming_speeches.iter()
+                .map(|(new_point, responses)| Iterator::chain(std::iter::once(new_point), responses.iter()).filter(is_by_speaker).count())
+                .sum::<usize>();
+    }
+
+    pub fn add_new_speech(&mut self, speaker_name: String, is_response: bool) -> AddSpeechResult {
+
+        if self.is_frozen() {
+            return AddSpeechResult::DiscussionIsFrozen;
+        }
+
+        if speaker_name.is_empty() {
+            return AddSpeechResult::EmptyName;
+        }
+
+        if is_response && self.current_new_point.is_none() && self.first_response_block.is_empty() {
+            return AddSpeechResult::NothingToRespondTo;
+        }
+
+        let speaker_name: String = self.resolve_name(speaker_name);
+
         // We are given a name and we need to turn that into a speaker object. We
         //  first check the list of speakers to see if a speaker with that name
         //  already exists. If it does we create a new pointer to them. Otherwise
-        //  we create a new speaker
-        let speaker: Arc<Mutex<Speaker>> = match self.speakers.get(&speaker_name) {
-            Some(speaker_p) => Arc::clone(&speaker_p),
+        //  we create a new speaker, but only once we know the speech can be
+        //  added, so that undoing it doesn't leave an extra speaker behind
+        let existing_speaker: Option<Arc<Mutex<Speaker>>> = self.speakers.get(&speaker_name).map(Arc::clone);
+
+        if let (Some(max_queued_speeches), Some(speaker)) = (self.max_queued_speeches_per_speaker, &existing_speaker) {
+            if self.count_queued_speeches(speaker) >= max_queued_speeches {
+                return AddSpeechResult::TooManyQueuedSpeeches;
+            }
+        }
+
+        self.remember();
+        self.touch();
+
+        let speaker: Arc<Mutex<Speaker>> = match existing_speaker {
+            Some(speaker_p) => speaker_p,
             None => {
                 let spkr = Arc::new(Mutex::new(Speaker::new(speaker_name.clone())));
-                self.speakers.insert(speaker_name, Arc::clone(&spkr));
+                self.speakers.insert(speaker_name.clone(), Arc::clone(&spkr));
                 spkr
             },
         };
 
         // We then create a new speech with the speaker from the previous step
-        let new_speech: Box<Speech> = Box::new(
+        let new_speech: SpeechId = self.speeches.insert(
             Speech{
+                id: SpeechId(self.next_speech_id),
                 speaker: Arc::clone(&speaker), 
+                given_name: speaker_name,
+                queued_at: Utc::now(),
                 duration: ZERO_SECONDS, 
-                fcfs_order: self.past_speeches.len() + self.upcoming_speeches.len()
             }
         );
+        self.next_speech_id += 1;
 
-        // We then add it to the speaking order in a way that makes sense
+        // We then add it to the speaking order in a way that makes sense. We
+        //  already know there is something to respond to if this is a response
         if is_response {
-            if self.current_new_point.is_some() || !self.first_response_block.is_empty() {
-                self.first_response_block.push_back(new_speech);
-            }
+            self.first_response_block.push_back(new_speech);
         } else if self.current_new_point.is_none() && self.first_response_block.is_empty() {
             self.current_new_point = Some(new_speech);
         } else {
-            self.upcoming_speeches.push_back((new_speech, LinkedList::new()));
+            self.upcoming_speeches.push_back((new_speech, VecDeque::new()));
         }
 
+        // If nothing else was happening then the new speech is under way
+        self.recount_speakers();
+
         // Finally, we may need to resort the speaking order so that our new
         //  speech ends up in the correct position.
         if self.priority_mode != PriorityMode::FirstComeFirstServe {
             self.resort_speaking_order();
         }
+
+        return AddSpeechResult::Success;
         
     }
 
+    pub fn add_chair(&mut self, token: String) {
+        self.participants.insert(token, Participant::chair());
+    }
+
+    // Returns true if `name1` and `name2` refer to the same person, either
+    //  because they are spelled the same (ignoring case) or because they have
+    //  been aliased to the same speaker
+    fn names_match(&self, name1: &str, name2: &str) -> bool {
+        if name1.to_lowercase() == name2.to_lowercase() {
+            return true;
+        }
+        match (self.speakers.get(name1), self.speakers.get(name2)) {
+            (Some(speaker1), Some(speaker2)) => return Arc::ptr_eq(speaker1, speaker2),
+            _ => return false,
+        }
+    }
+
+    /// Registers the holder of `token` under a display name. Each name can only
+    ///  be claimed by one session, so nobody can join as someone else
+    pub fn join(&mut self, token: String, name: String) -> JoinResult {
+
+        if self.is_frozen() {
+            return JoinResult::DiscussionIsFrozen;
+        }
+
+        if let Some(Participant { name: Some(_), .. }) = self.participants.get(&token) {
+            return JoinResult::AlreadyJoined;
+        }
+
+        let name: String = self.resolve_name(name);
+
+        let name_taken: bool = self.participants.values()
+            .filter_map(|participant| participant.name.as_ref())
+            .any(|other_name| self.names_match(&name, other_name));
+        if name_taken {
+            return JoinResult::NameTaken;
+        }
+
+        self.touch();
+
+        match self.participants.get_mut(&token) {
+            Some(participant) => participant.name = Some(name),
+            None => {
+                self.participants.insert(token, Participant::new(name));
+            },
+        }
+
+        return JoinResult::Success;
+    }
+
+    /// Adds a speech on behalf of the holder of `token`. Participants can only
+    ///  add speeches for themselves; the chair can name anyone in
+    ///  `speaker_name`, and leaving it out means the chair themselves.
+    pub fn add_speech_as(&mut self, token: &str, speaker_name: Option<String>, is_response: bool) -> AddSpeechResult {
+
+        let participant: Participant = match self.participants.get(token) {
+            Some(participant) => participant.clone(),
+            None => return AddSpeechResult::NotJoined,
+        };
+
+        match (speaker_name, participant.name) {
+            (Some(speaker_name), own_name) => {
+                let is_self: bool = own_name.is_some_and(|own_name| self.names_match(&speaker_name, &own_name));
+                if is_self || participant.role.can(Action::AddForOthers) {
+                    return self.add_new_speech(speaker_name, is_response);
+                } else {
+                    return AddSpeechResult::OnlyChairCanAddForOthers;
+                }
+            },
+            (None, Some(own_name)) => return self.add_new_speech(own_name, is_response),
+            (None, None) => return AddSpeechResult::NotJoined,
+        }
+
+    }
+
     pub fn goto_next_speech(&mut self) -> GotoSpeechResult {
+        let result: GotoSpeechResult = self.step_to_next_speech();
+        self.recount_speakers();
+        return result;
+    }
+
+    fn step_to_next_speech(&mut self) -> GotoSpeechResult {
         
         //////////////////////////////////////////////////////////////////////////
         //
@@ -390,6 +1109,17 @@ impl Discussion {
         //
         //////////////////////////////////////////////////////////////////////////
 
+        if self.is_frozen() {
+            return GotoSpeechResult::DiscussionIsFrozen;
+        }
+
+        // Pressing next with nothing left to say changes nothing, so there is
+        //  nothing to undo either
+        if self.current_speech().is_some() || !self.upcoming_speeches.is_empty() {
+            self.remember();
+        }
+        self.touch();
+
         match mem::take(&mut self.current_new_point) {
             Some(old_cnp) => if self.first_response_block.is_empty() {
                 // Case 4
@@ -397,20 +1127,20 @@ impl Discussion {
                     Some((next_np, next_rb)) => {
                         self.current_new_point = Some(next_np);
                         self.first_response_block = next_rb;
-                        self.past_speeches.push_back((old_cnp, LinkedList::new()));
+                        self.past_speeches.push_back((old_cnp, VecDeque::new()));
                         return GotoSpeechResult::Success;
                     },
                     None => {
                         self.current_new_point = None;
-                        self.first_response_block = LinkedList::new();
-                        self.past_speeches.push_back((old_cnp, LinkedList::new()));
+                        self.first_response_block = VecDeque::new();
+                        self.past_speeches.push_back((old_cnp, VecDeque::new()));
                         return GotoSpeechResult::NoSpeechToGoTo;
                     },
                 }
             } else {
                 // Case 1
                 self.current_new_point = None;
-                self.past_speeches.push_back((old_cnp, LinkedList::new()));
+                self.past_speeches.push_back((old_cnp, VecDeque::new()));
                 return GotoSpeechResult::Success;
             },
 
@@ -433,10 +1163,13 @@ impl Discussion {
                                 None => return GotoSpeechResult::NoSpeechToGoTo,
                             }
                         }
+
+                        // Case 2
+                        return GotoSpeechResult::Success;
                     },
                     None => {
                         debug_panic!();
-                        self.past_speeches.push_back((current_response, LinkedList::new()));
+                        self.past_speeches.push_back((current_response, VecDeque::new()));
                         return GotoSpeechResult::IllegalDiscussionSomehow;
                     },
                 }
@@ -451,39 +1184,452 @@ impl Discussion {
                 }
             }
         }
-        return GotoSpeechResult::IllegalDiscussionSomehow;
     }
 
     // TODO make this return a GotoSpeechResult and utilize it in the frontend
     pub fn goto_previous_speech(&mut self) {
+        if self.is_frozen() {
+            return;
+        }
+        self.touch();
+        if !self.past_speeches.is_empty() {
+            self.remember();
+        }
         if let Some((most_recent_new_point, mut most_recent_response_block)) = self.past_speeches.pop_back() {
             match most_recent_response_block.pop_back() {
                 Some(most_recent_response) => {
                     // If the current speech is a new point, then we need to push both it and all its responses into the upcoming speeches
-                    if let Some(current_new_point) = mem::replace(&mut self.current_new_point, None) {
-                        self.upcoming_speeches.push_front((current_new_point, mem::replace(&mut self.first_response_block, LinkedList::new())));
+                    if let Some(current_new_point) = self.current_new_point.take() {
+                        self.upcoming_speeches.push_front((current_new_point, std::mem::take(&mut self.first_response_block)));
                     }
                     self.first_response_block.push_front(most_recent_response);
                     self.past_speeches.push_back((most_recent_new_point, most_recent_response_block));
                 },
-                None => if let Some(current_new_point) = mem::replace(&mut self.current_new_point, Some(most_recent_new_point)) {
+                None => if let Some(current_new_point) = self.current_new_point.replace(most_recent_new_point) {
                     debug_assert!(most_recent_response_block.is_empty());
                     self.upcoming_speeches.push_front((current_new_point, mem::replace(&mut self.first_response_block, most_recent_response_block)));
                 },
             }
+            self.recount_speakers();
         }
     }
 
+    /// Adds a second to the discussion and to whoever is speaking, then brings
+    ///  the speaking order up to date in the modes other than first come first
+    ///  serve. In the brevity mode the speaker's upcoming speeches move back as
+    ///  their total grows.
     pub fn tick_clock(&mut self) {
-        if !self.paused {
+        if !self.paused && !self.is_frozen() {
             self.duration += ONE_SECOND;
-            match &mut self.current_new_point {
-                Some(current_new_point) => current_new_point.tick_clock(),
-                None => if let Some(current_response) = &mut self.first_response_block.front_mut() {
-                    current_response.tick_clock();
+            let speaker_arc: Option<Arc<Mutex<Speaker>>> = match self.current_speech().and_then(|id| self.speeches.get_mut(id)) {
+                Some(speech) => {
+                    speech.tick_clock();
+                    Some(Arc::clone(&speech.speaker))
                 },
+                None => None,
+            };
+            // Nobody else's totals have changed, so there is no need to count
+            //  everyone's speeches again
+            if let Some(speaker_arc) = &speaker_arc {
+                match speaker_arc.lock() {
+                    Ok(mut speaker) => speaker.total_speaking_time += ONE_SECOND,
+                    Err(e) => debug_panic!(e.to_string()),
+                }
+            }
+            match (self.priority_mode, speaker_arc) {
+                (PriorityMode::FirstComeFirstServe, _) => (),
+                _ if self.order_is_stale => self.resort_speaking_order(),
+                (PriorityMode::FavourBriefest, Some(speaker_arc)) => self.reposition_speeches_of(&speaker_arc),
+                _ => (),
             }
         }
     }
 
-}
\ No newline at end of file
+}
+#[test]
+fn test_closed_discussion_is_frozen() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut discussion = discussion.lock().unwrap();
+    discussion.add_new_speech("Alice".to_string(), false);
+    discussion.add_new_speech("Bob".to_string(), false);
+    discussion.close();
+    assert!(discussion.is_frozen());
+
+    assert_eq!(discussion.add_new_speech("Carol".to_string(), false), AddSpeechResult::DiscussionIsFrozen);
+    assert_eq!(discussion.upcoming_speeches.len(), 1);
+    assert_eq!(discussion.goto_next_speech(), GotoSpeechResult::DiscussionIsFrozen);
+    discussion.set_paused(true);
+    assert!(!discussion.paused);
+    discussion.tick_clock();
+    assert_eq!(discussion.duration, ZERO_SECONDS);
+}
+
+#[test]
+fn test_queued_speeches_are_capped() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings {
+        max_queued_speeches_per_speaker: Some(2),
+        ..DiscussionSettings::default()
+    });
+    let mut discussion = discussion.lock().unwrap();
+    assert_eq!(discussion.add_new_speech("Alice".to_string(), true), AddSpeechResult::NothingToRespondTo);
+
+    // The speech Alice is giving right now doesn't count against her
+    for _ in 0..3 {
+        assert_eq!(discussion.add_new_speech("Alice".to_string(), false), AddSpeechResult::Success);
+    }
+    assert_eq!(discussion.add_new_speech("Alice".to_string(), true), AddSpeechResult::TooManyQueuedSpeeches);
+    assert_eq!(discussion.add_new_speech("Bob".to_string(), true), AddSpeechResult::Success);
+
+    discussion.goto_next_speech();
+    discussion.goto_next_speech();
+    assert_eq!(discussion.add_new_speech("Alice".to_string(), true), AddSpeechResult::Success);
+}
+
+#[test]
+fn test_participants_speak_for_themselves() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut discussion = discussion.lock().unwrap();
+    discussion.add_chair("chair".to_string());
+
+    assert_eq!(discussion.add_speech_as("alice", None, false), AddSpeechResult::NotJoined);
+    assert_eq!(discussion.join("alice".to_string(), "Alice".to_string()), JoinResult::Success);
+    assert_eq!(discussion.join("alice".to_string(), "Bob".to_string()), JoinResult::AlreadyJoined);
+    assert_eq!(discussion.join("mallory".to_string(), "alice".to_string()), JoinResult::NameTaken);
+
+    assert_eq!(discussion.add_speech_as("alice", None, false), AddSpeechResult::Success);
+    assert_eq!(discussion.add_speech_as("alice", Some("alice".to_string()), false), AddSpeechResult::Success);
+    assert_eq!(discussion.add_speech_as("alice", Some("Bob".to_string()), false), AddSpeechResult::OnlyChairCanAddForOthers);
+
+    // The chair hasn't picked a name yet, but can still add for others
+    assert_eq!(discussion.add_speech_as("chair", None, false), AddSpeechResult::NotJoined);
+    assert_eq!(discussion.add_speech_as("chair", Some("Bob".to_string()), false), AddSpeechResult::Success);
+    assert_eq!(discussion.join("chair".to_string(), "Carol".to_string()), JoinResult::Success);
+    assert!(discussion.participants["chair"].is_chair());
+
+    // Aliased names belong to whoever joined under either of them
+    discussion.alias_speakers(&"Alice".to_string(), &"Ally".to_string());
+    assert_eq!(discussion.join("mallory".to_string(), "Ally".to_string()), JoinResult::NameTaken);
+    assert_eq!(discussion.add_speech_as("alice", Some("Ally".to_string()), false), AddSpeechResult::Success);
+}
+
+#[test]
+fn test_chairs_assign_roles() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut discussion = discussion.lock().unwrap();
+    discussion.add_chair("chair".to_string());
+    discussion.join("chair".to_string(), "Carol".to_string());
+    discussion.join("alice".to_string(), "Alice".to_string());
+    assert_eq!(discussion.role_of("alice"), Some(Role::Member));
+    assert_eq!(discussion.role_of("mallory"), None);
+
+    assert_eq!(discussion.assign_role("Bob", Role::Secretary), AssignRoleResult::NotJoined);
+    assert_eq!(discussion.assign_role("carol", Role::Member), AssignRoleResult::LastChair);
+    assert_eq!(discussion.assign_role("alice", Role::Chair), AssignRoleResult::Success);
+    assert!(discussion.is_chair("alice"));
+
+    // With a second chair, the first can step down
+    assert_eq!(discussion.assign_role("Carol", Role::ViceChair), AssignRoleResult::Success);
+    assert_eq!(discussion.role_of("chair"), Some(Role::ViceChair));
+    discussion.add_speech_as("alice", None, false);
+    assert_eq!(discussion.add_speech_as("chair", Some("Dave".to_string()), false), AddSpeechResult::OnlyChairCanAddForOthers);
+    assert_eq!(discussion.withdraw_speech_as("chair", SpeechId(0)), WithdrawSpeechResult::OnlyChairCanWithdrawForOthers);
+}
+
+#[test]
+fn test_typed_names_are_matched_against_the_roster() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings {
+        roster: Roster::from_csv("name,aliases\nAda Lovelace,Ada\nAlan Turing,\n").unwrap(),
+        ..DiscussionSettings::default()
+    });
+    let mut discussion = discussion.lock().unwrap();
+    let ada: Arc<Mutex<Speaker>> = Arc::clone(&discussion.speakers["Ada Lovelace"]);
+    assert!(Arc::ptr_eq(&ada, &discussion.speakers["Ada"]));
+
+    for typed in ["Ada Lovelace", "ada", "ADA LOVELACE", "Ada Lovelance"] {
+        assert_eq!(discussion.add_new_speech(typed.to_string(), false), AddSpeechResult::Success);
+    }
+    for (id, _) in discussion.speeches_in_order() {
+        assert!(Arc::ptr_eq(&discussion.speeches.get(id).unwrap().speaker, &ada));
+    }
+
+    // Names which aren't on the roster still work as they always have
+    assert_eq!(discussion.add_new_speech("Visitor".to_string(), false), AddSpeechResult::Success);
+    assert!(discussion.speakers.contains_key("Visitor"));
+
+    assert_eq!(discussion.join("t1".to_string(), "alan turing".to_string()), JoinResult::Success);
+    assert_eq!(discussion.participants["t1"].name.as_deref(), Some("Alan Turing"));
+}
+
+#[test]
+fn test_joining_a_series_keeps_the_meetings_own_roster() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings {
+        roster: Roster::from_csv("name,aliases\nAda Lovelace,Ada\n").unwrap(),
+        ..DiscussionSettings::default()
+    });
+    let mut discussion = discussion.lock().unwrap();
+    assert!(discussion.join_series(Series::new("committee".to_string(), Roster::from_csv("name,aliases\nAlan Turing,Alan\n").unwrap())));
+
+    assert_eq!(discussion.resolve_name("ada lovelace".to_string()), "Ada Lovelace");
+    assert_eq!(discussion.resolve_name("alan turing".to_string()), "Alan Turing");
+    assert_eq!(discussion.join("t1".to_string(), "Ada Lovelance".to_string()), JoinResult::Success);
+    assert_eq!(discussion.participants["t1"].name.as_deref(), Some("Ada Lovelace"));
+}
+
+#[test]
+fn test_split_undoes_a_mistaken_alias() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut discussion = discussion.lock().unwrap();
+    discussion.add_new_speech("Alice".to_string(), false);
+    for _ in 0..3 {
+        discussion.tick_clock();
+    }
+    discussion.add_new_speech("Alicia".to_string(), false);
+    discussion.add_new_speech("Alice".to_string(), false);
+    discussion.goto_next_speech();
+    for _ in 0..5 {
+        discussion.tick_clock();
+    }
+
+    discussion.alias_speakers(&"Alice".to_string(), &"Alicia".to_string());
+    let alice: Arc<Mutex<Speaker>> = Arc::clone(&discussion.speakers["Alice"]);
+    assert_eq!(alice.lock().unwrap().total_speaking_time, Duration::from_secs(8));
+
+    assert_eq!(discussion.split_speaker(&"Alice".to_string(), None), SplitSpeakerResult::NotAnAlias);
+    assert_eq!(discussion.split_speaker(&"Bob".to_string(), None), SplitSpeakerResult::UnknownName);
+    assert_eq!(discussion.split_speaker(&"Alicia".to_string(), Some(vec![SpeechId(3)])), SplitSpeakerResult::NoSuchSpeech(SpeechId(3)));
+    assert_eq!(discussion.split_speaker(&"Alicia".to_string(), None), SplitSpeakerResult::Success);
+
+    let alicia: Arc<Mutex<Speaker>> = Arc::clone(&discussion.speakers["Alicia"]);
+    assert!(!Arc::ptr_eq(&alice, &alicia));
+    assert!(alice.lock().unwrap().aliases.is_empty());
+    assert_eq!(alice.lock().unwrap().total_speaking_time, Duration::from_secs(3));
+    assert_eq!(alice.lock().unwrap().number_of_speeches_given, 1);
+    assert_eq!(alicia.lock().unwrap().total_speaking_time, Duration::from_secs(5));
+    assert_eq!(alicia.lock().unwrap().number_of_speeches_given, 1);
+    let speaker_names = |discussion: &Discussion| -> Vec<String> {
+        return discussion.speeches_in_order().iter().map(|(id, _)| discussion.speeches.get(*id).unwrap().speaker.lock().unwrap().name.clone()).collect();
+    };
+    let speakers: Vec<String> = speaker_names(&discussion);
+    assert_eq!(speakers, vec!["Alice", "Alicia", "Alice"]);
+
+    // Speeches can also be picked out by hand, whatever name they were added under
+    drop(alicia);
+    discussion.alias_speakers(&"Alice".to_string(), &"Alicia".to_string());
+    assert_eq!(discussion.split_speaker(&"Alicia".to_string(), Some(vec![SpeechId(0), SpeechId(1)])), SplitSpeakerResult::Success);
+    let speakers: Vec<String> = speaker_names(&discussion);
+    assert_eq!(speakers, vec!["Alicia", "Alicia", "Alice"]);
+    assert_eq!(discussion.speakers["Alicia"].lock().unwrap().total_speaking_time, Duration::from_secs(8));
+    assert_eq!(discussion.split_speaker(&"Alice".to_string(), Some(vec![SpeechId(0)])), SplitSpeakerResult::NotAnAlias);
+}
+
+#[test]
+fn test_withdrawing_speeches() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut discussion = discussion.lock().unwrap();
+    discussion.add_chair("chair".to_string());
+    discussion.join("alice".to_string(), "Alice".to_string());
+    discussion.join("bob".to_string(), "Bob".to_string());
+
+    // Speeches 0 to 5
+    for (token, is_response) in [("alice", false), ("bob", true), ("alice", true), ("bob", false), ("alice", false), ("bob", false)] {
+        assert_eq!(discussion.add_speech_as(token, None, is_response), AddSpeechResult::Success);
+    }
+    discussion.goto_next_speech();
+    assert_eq!(discussion.current_speech(), Some(SpeechId(1)));
+
+    assert_eq!(discussion.withdraw_speech_as("bob", SpeechId(0)), WithdrawSpeechResult::OnlyChairCanWithdrawForOthers);
+    assert_eq!(discussion.withdraw_speech_as("alice", SpeechId(0)), WithdrawSpeechResult::AlreadyGiven);
+    assert_eq!(discussion.withdraw_speech_as("bob", SpeechId(1)), WithdrawSpeechResult::AlreadyGiven);
+    assert_eq!(discussion.withdraw_speech_as("mallory", SpeechId(2)), WithdrawSpeechResult::NotJoined);
+    assert_eq!(discussion.withdraw_speech_as("alice", SpeechId(2)), WithdrawSpeechResult::Success);
+    assert_eq!(discussion.withdraw_speech_as("alice", SpeechId(2)), WithdrawSpeechResult::NoSuchSpeech);
+
+    // Withdrawing a new point takes the responses queued behind it along too
+    discussion.goto_next_speech();
+    assert_eq!(discussion.current_speech(), Some(SpeechId(3)));
+    discussion.add_speech_as("alice", None, true);
+    discussion.goto_previous_speech();
+    assert_eq!(discussion.withdraw_speech_as("chair", SpeechId(3)), WithdrawSpeechResult::Success);
+    let remaining: Vec<SpeechId> = discussion.speeches_in_order().iter().map(|(id, _)| *id).collect();
+    assert_eq!(remaining, vec![SpeechId(0), SpeechId(1), SpeechId(4), SpeechId(5)]);
+    assert!(discussion.speeches.get(SpeechId(6)).is_none());
+    assert_eq!(discussion.check_speaker_totals(), Ok(()));
+
+    // Withdrawn IDs are never handed out again
+    discussion.add_speech_as("bob", None, false);
+    let newest: &Speech = discussion.speeches.get(SpeechId(7)).unwrap();
+    assert!(newest.queued_at >= discussion.speeches.get(SpeechId(5)).unwrap().queued_at);
+}
+
+#[test]
+fn test_undo_and_redo() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut discussion = discussion.lock().unwrap();
+    assert_eq!(discussion.undo(), UndoResult::NothingToUndo);
+
+    discussion.add_new_speech("Alice".to_string(), false);
+    discussion.add_new_speech("Bob".to_string(), false);
+    discussion.add_new_speech("Robert".to_string(), false);
+    for _ in 0..2 {
+        discussion.tick_clock();
+    }
+    discussion.goto_next_speech();
+    discussion.tick_clock();
+    let speaker_of = |discussion: &Discussion, id: usize| Arc::clone(&discussion.speeches.get(SpeechId(id)).unwrap().speaker);
+    let order = |discussion: &Discussion| discussion.speeches_in_order();
+    let before_alias: Vec<(SpeechId, bool)> = order(&discussion);
+
+    // Undoing an alias gives both names their own speaker and totals back
+    discussion.alias_speakers(&"Bob".to_string(), &"Robert".to_string());
+    assert!(Arc::ptr_eq(&speaker_of(&discussion, 1), &speaker_of(&discussion, 2)));
+    assert_eq!(discussion.undo(), UndoResult::Success);
+    assert!(!Arc::ptr_eq(&speaker_of(&discussion, 1), &speaker_of(&discussion, 2)));
+    assert!(Arc::ptr_eq(&speaker_of(&discussion, 1), &discussion.speakers["Bob"]));
+    assert!(Arc::ptr_eq(&speaker_of(&discussion, 2), &discussion.speakers["Robert"]));
+    assert_eq!(order(&discussion), before_alias);
+
+    // Going back past the accidental next restores who is speaking and for how long
+    assert_eq!(discussion.undo(), UndoResult::Success);
+    assert_eq!(discussion.current_speech(), Some(SpeechId(0)));
+    assert_eq!(discussion.speakers["Alice"].lock().unwrap().total_speaking_time, Duration::from_secs(2));
+    assert_eq!(discussion.speakers["Alice"].lock().unwrap().number_of_speeches_given, 1);
+    assert_eq!(discussion.check_speaker_totals(), Ok(()));
+
+    // Redoing both brings the alias back, shared between both names
+    assert_eq!(discussion.redo(), UndoResult::Success);
+    assert_eq!(discussion.current_speech(), Some(SpeechId(1)));
+    assert_eq!(discussion.redo(), UndoResult::Success);
+    assert!(Arc::ptr_eq(&speaker_of(&discussion, 1), &speaker_of(&discussion, 2)));
+    assert!(Arc::ptr_eq(&discussion.speakers["Robert"], &discussion.speakers["Bob"]));
+    assert_eq!(discussion.redo(), UndoResult::NothingToRedo);
+
+    // Any new change throws away what could have been redone, and undone
+    //  speeches never give up their IDs
+    discussion.undo();
+    discussion.undo();
+    discussion.undo();
+    assert_eq!(order(&discussion).len(), 2);
+    discussion.set_priority_mode(PriorityMode::FavourBriefest);
+    assert!(!discussion.can_redo());
+    discussion.add_new_speech("Carol".to_string(), false);
+    assert!(discussion.speeches.get(SpeechId(3)).is_some());
+
+    // Only so many steps are kept
+    for _ in 0..history::UNDO_LIMIT + 10 {
+        discussion.set_paused(true);
+    }
+    let mut undone: usize = 0;
+    while discussion.undo() == UndoResult::Success {
+        undone += 1;
+    }
+    assert_eq!(undone, history::UNDO_LIMIT);
+
+    discussion.close();
+    assert_eq!(discussion.redo(), UndoResult::DiscussionIsFrozen);
+}
+
+#[cfg(test)]
+const TEST_NAMES: [&str; 4] = ["Alice", "Bob", "Carol", "Dave"];
+
+#[cfg(test)]
+#[derive(Debug, Clone)]
+enum Operation {
+    AddSpeech(usize, bool),
+    Next,
+    Previous,
+    Tick,
+    Alias(usize, usize),
+    Split(usize),
+    SetPriorityMode(PriorityMode),
+    SetPaused(bool),
+}
+
+#[cfg(test)]
+fn arbitrary_operation() -> impl proptest::strategy::Strategy<Value = Operation> {
+    use proptest::prelude::*;
+    let name = || 0..TEST_NAMES.len();
+    return prop_oneof![
+        (name(), any::<bool>()).prop_map(|(name, is_response)| Operation::AddSpeech(name, is_response)),
+        Just(Operation::Next),
+        Just(Operation::Previous),
+        Just(Operation::Tick),
+        Just(Operation::Tick),
+        (name(), name()).prop_map(|(name1, name2)| Operation::Alias(name1, name2)),
+        name().prop_map(Operation::Split),
+        prop_oneof![Just(PriorityMode::FirstComeFirstServe), Just(PriorityMode::FavourBriefest), Just(PriorityMode::FavourShiest)].prop_map(Operation::SetPriorityMode),
+        any::<bool>().prop_map(Operation::SetPaused),
+    ];
+}
+
+#[cfg(test)]
+impl Operation {
+    fn apply(&self, discussion: &mut Discussion) {
+        let name = |i: &usize| TEST_NAMES[*i].to_string();
+        match self {
+            Operation::AddSpeech(speaker, is_response) => {
+                discussion.add_new_speech(name(speaker), *is_response);
+            },
+            Operation::Next => {
+                discussion.goto_next_speech();
+            },
+            Operation::Previous => discussion.goto_previous_speech(),
+            Operation::Tick => discussion.tick_clock(),
+            Operation::Alias(name1, name2) => discussion.alias_speakers(&name(name1), &name(name2)),
+            Operation::Split(speaker) => {
+                discussion.split_speaker(&name(speaker), None);
+            },
+            Operation::SetPriorityMode(mode) => discussion.set_priority_mode(*mode),
+            Operation::SetPaused(paused) => discussion.set_paused(*paused),
+        }
+    }
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_speaker_totals_match_their_speeches(operations in proptest::collection::vec(arbitrary_operation(), 0..64)) {
+        // The clock thread holds its own `Arc` and keeps going for as long as
+        //  ours is alive, but it has to lock the discussion to tick. Holding
+        //  the lock for the whole test keeps it out, so that the only ticks
+        //  are the ones made by hand and the test stays deterministic.
+        let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+        let mut discussion = discussion.lock().unwrap();
+        for operation in &operations {
+            operation.apply(&mut discussion);
+            proptest::prop_assert_eq!(discussion.check_speaker_totals(), Ok(()), "after {:?}", operation);
+        }
+        let total: Duration = discussion.speeches_in_order().iter().map(|(id, _)| discussion.speeches.get(*id).unwrap().duration).sum();
+        proptest::prop_assert!(total <= discussion.duration);
+    }
+}
+
+#[test]
+fn test_ticks_order_speeches_as_a_full_resort_would() {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings {
+        priority_mode: PriorityMode::FavourBriefest,
+        ..DiscussionSettings::default()
+    });
+    let mut discussion = discussion.lock().unwrap();
+    let names: [&str; 4] = ["Alice", "Bob", "Carol", "Dave"];
+    for i in 0..24 {
+        discussion.add_new_speech(names[i * 7 % 4].to_string(), i % 3 == 2);
+    }
+
+    // Ticking is checked against sorting the order it started from, which is
+    //  what the clock used to do every second
+    for tick in 0..60 {
+        if tick % 5 == 4 {
+            discussion.goto_next_speech();
+        }
+        if tick % 11 == 10 {
+            discussion.add_new_speech(names[tick % 4].to_string(), tick % 2 == 0);
+        }
+        let before: ListOfSpeeches = discussion.upcoming_speeches.clone();
+        discussion.tick_clock();
+        let ticked: ListOfSpeeches = discussion.upcoming_speeches.clone();
+        discussion.upcoming_speeches = before;
+        discussion.resort_speaking_order();
+        assert_eq!(ticked, discussion.upcoming_speeches, "after tick {}", tick);
+        assert_eq!(discussion.check_speaker_totals(), Ok(()));
+    }
+}
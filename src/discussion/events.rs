@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::Discussion;
+use super::speech::{Speech, SpeechId};
+
+/// Things other systems might want to hear about (see `webhooks`). They aren't
+///  raised by the changes themselves: the discussion compares what it looks like
+///  now with what it looked like when it was last asked, so changes made by
+///  undoing, or by the clock, are reported the same way as everything else.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    SpeakerChanged {
+        speech: Option<SpeechId>,
+        speaker: Option<String>,
+    },
+    PointOfOrderRaised,
+    PointOfOrderResolved,
+    SpeechOverTime {
+        speech: SpeechId,
+        speaker: String,
+        time_limit: u64,
+    },
+    DiscussionClosed,
+}
+
+/// What the discussion looked like when its events were last taken
+#[derive(Debug, Default)]
+pub struct Observed {
+    current: Option<SpeechId>,
+    paused: bool,
+    frozen: bool,
+    // Each speech is only reported as over time once
+    over_time: Option<SpeechId>,
+}
+
+fn speaker_name(speech: &Speech) -> String {
+    match speech.speaker.lock() {
+        Ok(speaker) => return speaker.name.clone(),
+        Err(e) => return e.into_inner().name.clone(),
+    }
+}
+
+impl Discussion {
+
+    fn current_time_limit(&self) -> Option<Duration> {
+        match self.current_speech() {
+            Some(current) if self.current_new_point == Some(current) => return self.new_point_time_limit,
+            Some(_) => return self.response_time_limit,
+            None => return None,
+        }
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event> {
+
+        let mut events: Vec<Event> = Vec::new();
+        let current: Option<SpeechId> = self.current_speech();
+        let current_speech: Option<&Speech> = current.and_then(|id| self.speeches.get(id));
+
+        if current != self.observed.current {
+            events.push(Event::SpeakerChanged {
+                speech: current,
+                speaker: current_speech.map(speaker_name),
+            });
+        }
+
+        if self.paused != self.observed.paused {
+            events.push(if self.paused { Event::PointOfOrderRaised } else { Event::PointOfOrderResolved });
+        }
+
+        let mut over_time: Option<SpeechId> = self.observed.over_time;
+        if let (Some(speech), Some(time_limit)) = (current_speech, self.current_time_limit()) {
+            if speech.duration > time_limit && over_time != Some(speech.id) {
+                events.push(Event::SpeechOverTime {
+                    speech: speech.id,
+                    speaker: speaker_name(speech),
+                    time_limit: time_limit.as_secs(),
+                });
+                over_time = Some(speech.id);
+            }
+        }
+
+        if self.is_frozen() && !self.observed.frozen {
+            events.push(Event::DiscussionClosed);
+        }
+
+        self.observed = Observed {
+            current: current,
+            paused: self.paused,
+            frozen: self.is_frozen(),
+            over_time: over_time,
+        };
+        return events;
+    }
+
+}
+
+#[test]
+fn test_changes_are_reported_once() {
+    use std::sync::{Arc, Mutex};
+    use super::DiscussionSettings;
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings {
+        new_point_time_limit: Some(Duration::from_secs(2)),
+        ..DiscussionSettings::default()
+    });
+    let mut discussion = discussion.lock().unwrap();
+    assert_eq!(discussion.take_events(), vec![]);
+
+    discussion.add_new_speech("Alice".to_string(), false);
+    assert_eq!(discussion.take_events(), vec![Event::SpeakerChanged { speech: Some(SpeechId(0)), speaker: Some("Alice".to_string()) }]);
+    assert_eq!(discussion.take_events(), vec![]);
+
+    for _ in 0..3 {
+        discussion.tick_clock();
+    }
+    discussion.set_paused(true);
+    assert_eq!(discussion.take_events(), vec![
+        Event::PointOfOrderRaised,
+        Event::SpeechOverTime { speech: SpeechId(0), speaker: "Alice".to_string(), time_limit: 2 },
+    ]);
+    discussion.set_paused(false);
+    assert_eq!(discussion.take_events(), vec![Event::PointOfOrderResolved]);
+
+    // Undoing is a change like any other
+    for _ in 0..3 {
+        discussion.undo();
+    }
+    discussion.close();
+    assert_eq!(discussion.take_events(), vec![
+        Event::SpeakerChanged { speech: None, speaker: None },
+        Event::DiscussionClosed,
+    ]);
+}
@@ -0,0 +1,141 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use debug_panic::debug_panic;
+
+use super::{Discussion, ListOfSpeeches, PriorityMode, ResponseBlock};
+use super::motion::Motion;
+use super::roster::Roster;
+use super::series::Series;
+use super::speech::{Speaker, SpeechId, SpeechStore};
+
+// How many steps back the chair can go. Every step holds a full copy of the
+//  speeches, so this can't grow without bound
+pub const UNDO_LIMIT: usize = 100;
+
+// A copy of everything in a discussion that the chair can change. Joining,
+//  closing and the discussion's own clock are left out, since none of them
+//  are things you would want to take back with the speaking order.
+#[derive(Debug)]
+pub struct Snapshot {
+    speakers: HashMap<String, Arc<Mutex<Speaker>>>,
+    roster: Roster,
+    series: Option<Series>,
+    speeches: SpeechStore,
+    current_new_point: Option<SpeechId>,
+    first_response_block: ResponseBlock,
+    upcoming_speeches: ListOfSpeeches,
+    past_speeches: ListOfSpeeches,
+    motions: Vec<Motion>,
+    paused: bool,
+    priority_mode: PriorityMode,
+    rank_by_series: bool,
+}
+
+impl Snapshot {
+
+    // Speakers are shared between all of their names and speeches, so each
+    //  one is copied once and then handed out to everything that had the
+    //  original. Otherwise restoring an alias would give back two speakers.
+    pub fn take(discussion: &Discussion) -> Self {
+
+        let mut copies: HashMap<*const Mutex<Speaker>, Arc<Mutex<Speaker>>> = HashMap::new();
+        let mut copy = |speaker_arc: &Arc<Mutex<Speaker>>| -> Arc<Mutex<Speaker>> {
+            return Arc::clone(copies.entry(Arc::as_ptr(speaker_arc)).or_insert_with(|| match speaker_arc.lock() {
+                Ok(speaker) => Arc::new(Mutex::new(speaker.clone())),
+                Err(e) => {
+                    debug_panic!(e.to_string());
+                    Arc::new(Mutex::new(e.into_inner().clone()))
+                },
+            }));
+        };
+
+        let speakers: HashMap<String, Arc<Mutex<Speaker>>> = discussion.speakers.iter()
+            .map(|(name, speaker_arc)| (name.clone(), copy(speaker_arc)))
+            .collect();
+
+        let mut speeches: SpeechStore = discussion.speeches.clone();
+        for speech in speeches.iter_mut() {
+            speech.speaker = copy(&speech.speaker);
+        }
+
+        return Snapshot {
+            speakers: speakers,
+            roster: discussion.roster.clone(),
+            series: discussion.series.clone(),
+            speeches: speeches,
+            current_new_point: discussion.current_new_point,
+            first_response_block: discussion.first_response_block.clone(),
+            upcoming_speeches: discussion.upcoming_speeches.clone(),
+            past_speeches: discussion.past_speeches.clone(),
+            motions: discussion.motions.clone(),
+            paused: discussion.paused,
+            priority_mode: discussion.priority_mode,
+            rank_by_series: discussion.rank_by_series,
+        };
+    }
+
+    pub fn restore(self, discussion: &mut Discussion) {
+        discussion.speakers = self.speakers;
+        discussion.roster = self.roster;
+        discussion.series = self.series;
+        discussion.speeches = self.speeches;
+        discussion.current_new_point = self.current_new_point;
+        discussion.first_response_block = self.first_response_block;
+        discussion.upcoming_speeches = self.upcoming_speeches;
+        discussion.past_speeches = self.past_speeches;
+        discussion.motions = self.motions;
+        discussion.paused = self.paused;
+        discussion.priority_mode = self.priority_mode;
+        discussion.rank_by_series = self.rank_by_series;
+    }
+
+}
+
+// The discussion records a snapshot just before each change. Undoing swaps the
+//  discussion back to the latest one and keeps the state it left behind for
+//  redoing; making any new change throws the redo steps away.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+
+    pub fn record(&mut self, snapshot: Snapshot) {
+        self.undo.push_back(snapshot);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return !self.undo.is_empty();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return !self.redo.is_empty();
+    }
+
+    // Both of these hand back the snapshot to restore, having put `current`
+    //  where the opposite operation will find it
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let previous: Snapshot = self.undo.pop_back()?;
+        self.redo.push(current);
+        return Some(previous);
+    }
+
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let next: Snapshot = self.redo.pop()?;
+        self.undo.push_back(current);
+        return Some(next);
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+}
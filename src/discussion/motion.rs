@@ -0,0 +1,134 @@
+use chrono::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use super::Discussion;
+use super::speech::SpeechId;
+
+/// A proposal put to the meeting. The chair records it when it is moved and
+///  again once it has been dealt with; the voting itself happens outside
+///  Speakeasy. Motions are numbered from zero in the order they were moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Motion {
+    pub text: String,
+    pub moved_by: String,
+    pub moved_at: DateTime<Utc>,
+    /// Whichever speech was being given when the motion was moved
+    pub during_speech: Option<SpeechId>,
+    pub outcome: Option<MotionOutcome>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionOutcome {
+    Carried,
+    Defeated,
+    Withdrawn,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MotionResult {
+    Success,
+    EmptyText,
+    EmptyName,
+    NoSuchMotion,
+    AlreadyResolved,
+    DiscussionIsFrozen,
+}
+
+impl Discussion {
+
+    // Movers are recorded under the name of the speaker they are, so that an
+    //  alias or a near miss of a roster name still finds the same person
+    fn mover_name(&self, typed: String) -> String {
+        let name: String = self.resolve_name(typed);
+        match self.speakers.get(&name).map(|speaker| speaker.lock()) {
+            Some(Ok(speaker)) => return speaker.name.clone(),
+            Some(Err(e)) => return e.into_inner().name.clone(),
+            None => return name,
+        }
+    }
+
+    /// Records a motion moved by `moved_by`, who needn't have spoken
+    pub fn move_motion(&mut self, text: String, moved_by: String) -> MotionResult {
+
+        if self.is_frozen() {
+            return MotionResult::DiscussionIsFrozen;
+        }
+        if text.trim().is_empty() {
+            return MotionResult::EmptyText;
+        }
+        if moved_by.is_empty() {
+            return MotionResult::EmptyName;
+        }
+
+        self.remember();
+        let motion: Motion = Motion {
+            text: text,
+            moved_by: self.mover_name(moved_by),
+            moved_at: Utc::now(),
+            during_speech: self.current_speech(),
+            outcome: None,
+            resolved_at: None,
+        };
+        self.motions.push(motion);
+        self.touch();
+        return MotionResult::Success;
+    }
+
+    pub fn resolve_motion(&mut self, number: usize, outcome: MotionOutcome) -> MotionResult {
+
+        if self.is_frozen() {
+            return MotionResult::DiscussionIsFrozen;
+        }
+        match self.motions.get(number) {
+            Some(motion) if motion.outcome.is_some() => return MotionResult::AlreadyResolved,
+            Some(_) => (),
+            None => return MotionResult::NoSuchMotion,
+        }
+
+        self.remember();
+        if let Some(motion) = self.motions.get_mut(number) {
+            motion.outcome = Some(outcome);
+            motion.resolved_at = Some(Utc::now());
+        }
+        self.touch();
+        return MotionResult::Success;
+    }
+
+}
+
+#[test]
+fn test_motions_are_moved_and_resolved() {
+    use std::sync::{Arc, Mutex};
+    use super::DiscussionSettings;
+    use super::roster::Roster;
+
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings {
+        roster: Roster::from_csv("name,aliases\nAda Lovelace,Ada\n").unwrap(),
+        ..DiscussionSettings::default()
+    });
+    let mut discussion = discussion.lock().unwrap();
+    assert_eq!(discussion.move_motion(" ".to_string(), "Ada".to_string()), MotionResult::EmptyText);
+    assert_eq!(discussion.move_motion("That we adjourn".to_string(), "".to_string()), MotionResult::EmptyName);
+
+    discussion.add_new_speech("Alan".to_string(), false);
+    assert_eq!(discussion.move_motion("That the report be adopted".to_string(), "Ada".to_string()), MotionResult::Success);
+    assert_eq!(discussion.motions[0].moved_by, "Ada Lovelace");
+    assert_eq!(discussion.motions[0].during_speech, Some(SpeechId(0)));
+
+    assert_eq!(discussion.resolve_motion(1, MotionOutcome::Carried), MotionResult::NoSuchMotion);
+    assert_eq!(discussion.resolve_motion(0, MotionOutcome::Carried), MotionResult::Success);
+    assert_eq!(discussion.resolve_motion(0, MotionOutcome::Defeated), MotionResult::AlreadyResolved);
+    assert_eq!(discussion.motions[0].outcome, Some(MotionOutcome::Carried));
+    assert!(discussion.motions[0].resolved_at.is_some());
+
+    // Motions are part of what the chair can take back
+    discussion.undo();
+    assert_eq!(discussion.motions[0].outcome, None);
+    discussion.undo();
+    assert!(discussion.motions.is_empty());
+
+    discussion.close();
+    assert_eq!(discussion.move_motion("That we adjourn".to_string(), "Ada".to_string()), MotionResult::DiscussionIsFrozen);
+}
@@ -0,0 +1,283 @@
+// A reference model of the speaking order, used to check `goto_next_speech`,
+//  `goto_previous_speech` and friends against random sequences of operations.
+//
+// The model flattens the whole discussion into a single list of speeches in
+//  speaking order (past, current, then upcoming) and keeps the index of the
+//  current speech. Going forwards or backwards is then just moving the index,
+//  which is easy to believe is right. After every step the real discussion is
+//  flattened the same way and the two have to agree exactly.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use proptest::prelude::*;
+
+use super::*;
+
+const NAMES: [&str; 4] = ["Alice", "Bob", "Carol", "Dave"];
+
+#[derive(Debug, Clone)]
+enum Step {
+    AddNewPoint(usize),
+    AddResponse(usize),
+    Next,
+    Previous,
+    NextThenPrevious,
+    Tick,
+    Alias(usize, usize),
+    SetPriorityMode(PriorityMode),
+}
+
+fn arbitrary_step() -> impl Strategy<Value = Step> {
+    let name = || 0..NAMES.len();
+    return prop_oneof![
+        3 => name().prop_map(Step::AddNewPoint),
+        3 => name().prop_map(Step::AddResponse),
+        3 => Just(Step::Next),
+        2 => Just(Step::Previous),
+        2 => Just(Step::NextThenPrevious),
+        3 => Just(Step::Tick),
+        1 => (name(), name()).prop_map(|(name1, name2)| Step::Alias(name1, name2)),
+        1 => prop_oneof![Just(PriorityMode::FirstComeFirstServe), Just(PriorityMode::FavourBriefest), Just(PriorityMode::FavourShiest)].prop_map(Step::SetPriorityMode),
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ModelSpeech {
+    id: SpeechId,
+    added: usize,
+    name: usize,
+    is_response: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Model {
+    speeches: Vec<ModelSpeech>,
+    // Equal to `speeches.len()` when nothing is being said
+    current: usize,
+    priority_mode: PriorityMode,
+    // Which names have been aliased together, as a union-find over `NAMES`
+    same_speaker_as: [usize; 4],
+}
+
+impl Model {
+
+    fn new() -> Self {
+        Model {
+            speeches: Vec::new(),
+            current: 0,
+            priority_mode: PriorityMode::FirstComeFirstServe,
+            same_speaker_as: [0, 1, 2, 3],
+        }
+    }
+
+    fn speaker(&self, name: usize) -> usize {
+        let mut name: usize = name;
+        while self.same_speaker_as[name] != name {
+            name = self.same_speaker_as[name];
+        }
+        return name;
+    }
+
+    // The index just past the last speech in the block (a new point and its
+    //  responses) which the current speech belongs to
+    fn end_of_current_block(&self) -> usize {
+        return (self.current + 1..self.speeches.len())
+            .find(|i| !self.speeches[*i].is_response)
+            .unwrap_or(self.speeches.len());
+    }
+
+}
+
+// The real discussion flattened the same way as the model
+#[derive(Debug, PartialEq, Eq)]
+struct Flattened {
+    speeches: Vec<(SpeechId, bool)>,
+    current: usize,
+}
+
+fn flatten(discussion: &Discussion) -> Flattened {
+    let mut speeches: Vec<(SpeechId, bool)> = Vec::new();
+    for (new_point, response_block) in &discussion.past_speeches {
+        speeches.push((*new_point, false));
+        speeches.extend(response_block.iter().map(|response| (*response, true)));
+    }
+    let current: usize = speeches.len();
+    if let Some(current_new_point) = discussion.current_new_point {
+        speeches.push((current_new_point, false));
+    }
+    speeches.extend(discussion.first_response_block.iter().map(|response| (*response, true)));
+    for (new_point, response_block) in &discussion.upcoming_speeches {
+        speeches.push((*new_point, false));
+        speeches.extend(response_block.iter().map(|response| (*response, true)));
+    }
+    return Flattened { speeches: speeches, current: current };
+}
+
+fn check(model: &Model, discussion: &Discussion) -> Result<(), TestCaseError> {
+
+    let flattened: Flattened = flatten(discussion);
+    let expected: Vec<(SpeechId, bool)> = model.speeches.iter().map(|speech| (speech.id, speech.is_response)).collect();
+    prop_assert_eq!(&flattened.speeches, &expected, "the speaking order differs from the model");
+    prop_assert_eq!(flattened.current, model.current, "the current speech differs from the model");
+
+    // The first speech of the current block can only be a response if it is
+    //  being given right now, with its new point in the past
+    if discussion.current_new_point.is_none() && !discussion.first_response_block.is_empty() {
+        prop_assert!(!discussion.past_speeches.is_empty(), "a response block has no new point");
+    }
+
+    // Two speeches are by the same speaker exactly when their names have been
+    //  aliased together
+    for a in &model.speeches {
+        for b in &model.speeches {
+            let (a_speech, b_speech) = (discussion.speeches.get(a.id).unwrap(), discussion.speeches.get(b.id).unwrap());
+            prop_assert_eq!(
+                Arc::ptr_eq(&a_speech.speaker, &b_speech.speaker),
+                model.speaker(a.name) == model.speaker(b.name),
+                "{} and {} are mixed up", NAMES[a.name], NAMES[b.name],
+            );
+        }
+    }
+
+    return Ok(());
+}
+
+// Re-sorting only ever moves whole upcoming blocks around, and responses
+//  within them. In first come first serve order that has to put everything
+//  back in the order it was added; otherwise we check that the real order is
+//  a rearrangement of the model's, sorted by the mode's key, and then adopt it.
+fn resort(model: &mut Model, discussion: &Discussion) -> Result<(), TestCaseError> {
+
+    let start: usize = if model.current < model.speeches.len() { model.end_of_current_block() } else { model.speeches.len() };
+
+    let mut blocks: Vec<Vec<ModelSpeech>> = Vec::new();
+    for speech in model.speeches.drain(start..) {
+        match blocks.last_mut() {
+            Some(block) if speech.is_response => block.push(speech),
+            _ => blocks.push(vec![speech]),
+        }
+    }
+
+    match model.priority_mode {
+        PriorityMode::FirstComeFirstServe => {
+            for block in &mut blocks {
+                block[1..].sort_by_key(|speech| speech.added);
+            }
+            blocks.sort_by_key(|block| block[0].added);
+            model.speeches.extend(blocks.into_iter().flatten());
+        },
+        PriorityMode::FavourBriefest | PriorityMode::FavourShiest => {
+            let by_id: HashMap<SpeechId, ModelSpeech> = blocks.into_iter().flatten().map(|speech| (speech.id, speech)).collect();
+            let flattened: Flattened = flatten(discussion);
+            let upcoming: &[(SpeechId, bool)] = &flattened.speeches[usize::min(start, flattened.speeches.len())..];
+            prop_assert_eq!(upcoming.len(), by_id.len(), "re-sorting lost or duplicated a speech");
+            for (speech_id, is_response) in upcoming {
+                match by_id.get(speech_id) {
+                    Some(speech) if speech.is_response == *is_response => model.speeches.push(speech.clone()),
+                    _ => return Err(TestCaseError::fail("re-sorting moved a speech into another block")),
+                }
+            }
+            let new_points: Vec<(Duration, usize)> = discussion.upcoming_speeches.iter().map(|(new_point, _)| discussion.sort_key(*new_point)).collect();
+            prop_assert!(new_points.windows(2).all(|pair| pair[0] <= pair[1]), "upcoming new points aren't sorted: {:?}", new_points);
+        },
+    }
+
+    return Ok(());
+}
+
+fn apply(step: &Step, model: &mut Model, discussion: &mut Discussion) -> Result<(), TestCaseError> {
+
+    let add = |model: &mut Model, discussion: &mut Discussion, name: usize, is_response: bool| -> Result<(), TestCaseError> {
+        let before: Vec<SpeechId> = model.speeches.iter().map(|speech| speech.id).collect();
+        let result: AddSpeechResult = discussion.add_new_speech(NAMES[name].to_string(), is_response);
+        if is_response && model.current == model.speeches.len() {
+            prop_assert_eq!(result, AddSpeechResult::NothingToRespondTo);
+            return Ok(());
+        }
+        prop_assert_eq!(result, AddSpeechResult::Success);
+        let added: Vec<SpeechId> = flatten(discussion).speeches.into_iter().map(|(speech_id, _)| speech_id).filter(|speech_id| !before.contains(speech_id)).collect();
+        prop_assert_eq!(added.len(), 1, "adding one speech added {}", added.len());
+        prop_assert!(before.iter().all(|speech_id| *speech_id < added[0]), "{:?} was added after a speech with a later ID", added[0]);
+        let speech: ModelSpeech = ModelSpeech { id: added[0], added: model.speeches.len(), name: name, is_response: is_response };
+        if is_response {
+            let end: usize = model.end_of_current_block();
+            model.speeches.insert(end, speech);
+        } else {
+            model.speeches.push(speech);
+        }
+        if model.priority_mode != PriorityMode::FirstComeFirstServe {
+            resort(model, discussion)?;
+        }
+        return Ok(());
+    };
+
+    let next = |model: &mut Model, discussion: &mut Discussion| -> Result<(), TestCaseError> {
+        let result: GotoSpeechResult = discussion.goto_next_speech();
+        if model.current < model.speeches.len() {
+            model.current += 1;
+        }
+        if model.current < model.speeches.len() {
+            prop_assert_eq!(result, GotoSpeechResult::Success);
+        } else {
+            prop_assert_eq!(result, GotoSpeechResult::NoSpeechToGoTo);
+        }
+        return Ok(());
+    };
+
+    let previous = |model: &mut Model, discussion: &mut Discussion| {
+        discussion.goto_previous_speech();
+        if model.current > 0 {
+            model.current -= 1;
+        }
+    };
+
+    match step {
+        Step::AddNewPoint(name) => add(model, discussion, *name, false)?,
+        Step::AddResponse(name) => add(model, discussion, *name, true)?,
+        Step::Next => next(model, discussion)?,
+        Step::Previous => previous(model, discussion),
+        Step::NextThenPrevious => {
+            if model.current < model.speeches.len() {
+                let before: Flattened = flatten(discussion);
+                next(model, discussion)?;
+                check(model, discussion)?;
+                previous(model, discussion);
+                prop_assert_eq!(flatten(discussion), before, "going forwards then backwards changed the speaking order");
+            }
+        },
+        // Outside first come first serve, ticking brings the order up to date
+        //  with the speaking time
+        Step::Tick => {
+            discussion.tick_clock();
+            if model.priority_mode != PriorityMode::FirstComeFirstServe {
+                resort(model, discussion)?;
+            }
+        },
+        Step::Alias(name1, name2) => {
+            discussion.alias_speakers(&NAMES[*name1].to_string(), &NAMES[*name2].to_string());
+            let (speaker1, speaker2) = (model.speaker(*name1), model.speaker(*name2));
+            model.same_speaker_as[speaker2] = speaker1;
+        },
+        Step::SetPriorityMode(mode) => {
+            discussion.set_priority_mode(*mode);
+            model.priority_mode = *mode;
+            resort(model, discussion)?;
+        },
+    }
+
+    return Ok(());
+}
+
+proptest! {
+    #[test]
+    fn test_speaking_order_matches_model(steps in proptest::collection::vec(arbitrary_step(), 0..48)) {
+        let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+        let mut discussion = discussion.lock().unwrap();
+        let mut model: Model = Model::new();
+        for step in &steps {
+            apply(step, &mut model, &mut discussion)?;
+            check(&model, &discussion)?;
+        }
+    }
+}
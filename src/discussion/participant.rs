@@ -0,0 +1,108 @@
+use serde::{Serialize, Deserialize};
+
+/// Someone who holds a session in a discussion. Participants join under a
+///  display name and from then on every speech they add is given under that
+///  name. What else they can do depends on their role (see `PERMISSIONS`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Participant {
+    pub name: Option<String>,
+    pub role: Role,
+}
+
+impl Participant {
+
+    pub fn new(name: String) -> Self {
+        Self {
+            name: Some(name),
+            role: Role::Member,
+        }
+    }
+
+    /// The chair's session exists before they have picked a name for
+    ///  themselves
+    pub fn chair() -> Self {
+        Self {
+            name: None,
+            role: Role::Chair,
+        }
+    }
+
+    pub fn is_chair(&self) -> bool {
+        return self.role == Role::Chair;
+    }
+
+}
+
+/// Whoever creates a discussion is its chair, and everyone who joins after
+///  them is a member until a chair gives them another role. There can be more
+///  than one chair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Chair,
+    #[serde(alias = "timekeeper")]
+    ViceChair,
+    Secretary,
+    Member,
+}
+
+/// Everything which only some roles are allowed to do. Adding and withdrawing
+///  your own speeches isn't in here, since anybody who has joined can do that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Next,
+    Previous,
+    Pause,
+    SetPriorityMode,
+    Alias,
+    Split,
+    AddForOthers,
+    WithdrawForOthers,
+    Undo,
+    Motions,
+    UploadRoster,
+    AssignRoles,
+    Export,
+    ShareDisplay,
+    Close,
+    Archive,
+}
+
+const PERMISSIONS: [(Action, &[Role]); 16] = [
+    (Action::Next, &[Role::Chair, Role::ViceChair]),
+    (Action::Previous, &[Role::Chair, Role::ViceChair]),
+    (Action::Pause, &[Role::Chair, Role::ViceChair]),
+    (Action::SetPriorityMode, &[Role::Chair]),
+    (Action::Alias, &[Role::Chair, Role::Secretary]),
+    (Action::Split, &[Role::Chair, Role::Secretary]),
+    (Action::AddForOthers, &[Role::Chair]),
+    (Action::WithdrawForOthers, &[Role::Chair]),
+    (Action::Undo, &[Role::Chair]),
+    (Action::Motions, &[Role::Chair, Role::Secretary]),
+    (Action::UploadRoster, &[Role::Chair]),
+    (Action::AssignRoles, &[Role::Chair]),
+    (Action::Export, &[Role::Chair, Role::Secretary]),
+    (Action::ShareDisplay, &[Role::Chair, Role::ViceChair]),
+    (Action::Close, &[Role::Chair]),
+    (Action::Archive, &[Role::Chair]),
+];
+
+impl Role {
+
+    pub fn can(self, action: Action) -> bool {
+        return PERMISSIONS.iter().any(|(permitted, roles)| *permitted == action && roles.contains(&self));
+    }
+
+}
+
+#[test]
+fn test_every_action_has_a_chair() {
+    let actions: Vec<Action> = PERMISSIONS.iter().map(|(action, _)| *action).collect();
+    for action in &actions {
+        assert!(Role::Chair.can(*action), "{:?}", action);
+        assert!(!Role::Member.can(*action), "{:?}", action);
+        assert_eq!(actions.iter().filter(|other| *other == action).count(), 1, "{:?} is listed twice", action);
+    }
+    assert!(Role::ViceChair.can(Action::Pause) && !Role::ViceChair.can(Action::Alias));
+    assert!(Role::Secretary.can(Action::Export) && !Role::Secretary.can(Action::Next));
+}
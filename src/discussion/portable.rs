@@ -0,0 +1,666 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::prelude::*;
+use debug_panic::debug_panic;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use super::{Discussion, DiscussionSettings, Lifecycle, PriorityMode};
+use super::motion::{Motion, MotionOutcome};
+use super::roster::Roster;
+use super::series::Series;
+use super::speech::{Speaker, Speech, SpeechId};
+
+/// The version written by `PortableDiscussion::new`. Files written by older
+///  versions are migrated forward when they are read (see `migrate`), so this
+///  only goes up when the format changes in a way old readers can't follow.
+///
+/// - 0: the record `storage::write_archive` writes. It has no `version` field,
+///   and knows nothing of the settings other than the priority mode, or of the
+///   names speeches were added under.
+/// - 1: everything in `PortableDiscussion`.
+pub const FORMAT_VERSION: u64 = 1;
+
+// Each speech sits at the index of its ID (see `SpeechStore`), so IDs handed
+//  out to speeches which have since been withdrawn still take up room. A file
+//  can't claim more of those than this, or importing it could take up any
+//  amount of memory.
+const MAX_UNUSED_SPEECH_IDS: usize = 100_000;
+
+/// Everything needed to carry on a discussion somewhere else: its settings,
+///  every speaker and speech, where each speech is in the speaking order, and
+///  whether it is paused or closed. Durations are in whole seconds and times
+///  are RFC 3339.
+///
+/// Sessions are left out, since their tokens are as good as passwords. Whoever
+///  imports a discussion becomes its chair and everyone else joins again. The
+///  undo history and the projector's display token aren't carried over either.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableDiscussion {
+    pub version: u64,
+    pub exported_at: String,
+    pub settings: PortableSettings,
+    pub lifecycle: Lifecycle,
+    pub paused: bool,
+    pub duration: u64,
+    pub next_speech_id: usize,
+    pub speakers: Vec<PortableSpeaker>,
+    pub speeches: Vec<PortableSpeech>,
+    pub order: PortableOrder,
+    #[serde(default)]
+    pub motions: Vec<PortableMotion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableSettings {
+    pub priority_mode: PriorityMode,
+    pub new_point_time_limit: Option<u64>,
+    pub response_time_limit: Option<u64>,
+    pub max_queued_speeches_per_speaker: Option<usize>,
+    #[serde(default)]
+    pub roster: Roster,
+    #[serde(default)]
+    pub series: Option<Series>,
+    #[serde(default)]
+    pub rank_by_series: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableSpeaker {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// `speaker` is the speaker's name (not an alias); `given_name` is the name the
+///  speech was added under (see `Speech`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableSpeech {
+    pub id: SpeechId,
+    pub speaker: String,
+    pub given_name: String,
+    pub queued_at: String,
+    pub duration: u64,
+}
+
+/// The speaking order as the discussion holds it. `current_responses` are the
+///  responses to the current new point, the first of which is being given if
+///  there is no `current_new_point`; in that case the new point they respond to
+///  is the last of `past`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableOrder {
+    pub past: Vec<PortableBlock>,
+    pub current_new_point: Option<SpeechId>,
+    pub current_responses: Vec<SpeechId>,
+    pub upcoming: Vec<PortableBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableBlock {
+    pub new_point: SpeechId,
+    #[serde(default)]
+    pub responses: Vec<SpeechId>,
+}
+
+/// Motions are listed in the order they were moved, which is what numbers them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableMotion {
+    pub text: String,
+    pub moved_by: String,
+    pub moved_at: String,
+    #[serde(default)]
+    pub during_speech: Option<SpeechId>,
+    #[serde(default)]
+    pub outcome: Option<MotionOutcome>,
+    #[serde(default)]
+    pub resolved_at: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PortableError {
+    Malformed(String),
+    TooNew(u64),
+    Inconsistent(String),
+    Invalid(String),
+}
+
+impl fmt::Display for PortableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortableError::Malformed(e) => write!(f, "the discussion could not be read: {}", e),
+            PortableError::TooNew(version) => write!(f, "the discussion was saved in format version {}, but this server only understands up to version {}", version, FORMAT_VERSION),
+            PortableError::Inconsistent(e) => write!(f, "the discussion doesn't add up: {}", e),
+            PortableError::Invalid(e) => write!(f, "the discussion can't be used here: {}", e),
+        }
+    }
+}
+
+fn seconds(duration: Option<Duration>) -> Option<u64> {
+    return duration.map(|duration| duration.as_secs());
+}
+
+fn block((new_point, responses): &(SpeechId, VecDeque<SpeechId>)) -> PortableBlock {
+    return PortableBlock {
+        new_point: *new_point,
+        responses: responses.iter().copied().collect(),
+    };
+}
+
+fn unblock(block: PortableBlock) -> (SpeechId, VecDeque<SpeechId>) {
+    return (block.new_point, VecDeque::from(block.responses));
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    return DateTime::parse_from_rfc3339(time).ok().map(|time| time.with_timezone(&Utc));
+}
+
+impl PortableDiscussion {
+
+    pub fn new(discussion: &Discussion) -> Self {
+
+        // Every name a speaker goes by maps to the same `Arc`
+        let mut seen: Vec<&Arc<Mutex<Speaker>>> = Vec::new();
+        let mut speakers: Vec<PortableSpeaker> = Vec::new();
+        for speaker_arc in discussion.speakers.values() {
+            if seen.iter().any(|s| Arc::ptr_eq(s, speaker_arc)) {
+                continue;
+            }
+            seen.push(speaker_arc);
+            match speaker_arc.lock() {
+                Ok(speaker) => {
+                    let mut aliases: Vec<String> = speaker.aliases.iter().cloned().collect();
+                    aliases.sort();
+                    speakers.push(PortableSpeaker { name: speaker.name.clone(), aliases: aliases });
+                },
+                Err(e) => debug_panic!(e.to_string()),
+            }
+        }
+        speakers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let speeches: Vec<PortableSpeech> = discussion.speeches.iter().map(|speech| PortableSpeech {
+            id: speech.id,
+            speaker: match speech.speaker.lock() {
+                Ok(speaker) => speaker.name.clone(),
+                Err(e) => e.into_inner().name.clone(),
+            },
+            given_name: speech.given_name.clone(),
+            queued_at: speech.queued_at.to_rfc3339(),
+            duration: speech.duration.as_secs(),
+        }).collect();
+
+        return PortableDiscussion {
+            version: FORMAT_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            settings: PortableSettings {
+                priority_mode: discussion.priority_mode,
+                new_point_time_limit: seconds(discussion.new_point_time_limit),
+                response_time_limit: seconds(discussion.response_time_limit),
+                max_queued_speeches_per_speaker: discussion.max_queued_speeches_per_speaker,
+                roster: discussion.roster.clone(),
+                series: discussion.series.clone(),
+                rank_by_series: discussion.rank_by_series,
+            },
+            lifecycle: discussion.lifecycle,
+            paused: discussion.paused,
+            duration: discussion.duration.as_secs(),
+            next_speech_id: discussion.next_speech_id,
+            speakers: speakers,
+            speeches: speeches,
+            order: PortableOrder {
+                past: discussion.past_speeches.iter().map(block).collect(),
+                current_new_point: discussion.current_new_point,
+                current_responses: discussion.first_response_block.iter().copied().collect(),
+                upcoming: discussion.upcoming_speeches.iter().map(block).collect(),
+            },
+            motions: discussion.motions.iter().map(|motion| PortableMotion {
+                text: motion.text.clone(),
+                moved_by: motion.moved_by.clone(),
+                moved_at: motion.moved_at.to_rfc3339(),
+                during_speech: motion.during_speech,
+                outcome: motion.outcome,
+                resolved_at: motion.resolved_at.map(|resolved_at| resolved_at.to_rfc3339()),
+            }).collect(),
+        };
+    }
+
+    /// Reads a discussion saved in any version of the format up to
+    ///  `FORMAT_VERSION`, including an archive record
+    pub fn from_json(json: &str) -> Result<Self, PortableError> {
+        let value: Value = match serde_json::from_str(json) {
+            Ok(value) => value,
+            Err(e) => return Err(PortableError::Malformed(e.to_string())),
+        };
+        match serde_json::from_value(migrate(value)?) {
+            Ok(portable) => return Ok(portable),
+            Err(e) => return Err(PortableError::Malformed(e.to_string())),
+        }
+    }
+
+    // Every speech has to be somewhere in the speaking order exactly once, every
+    //  name has to lead to exactly one speaker, and every speech ID has to have
+    //  been handed out before `next_speech_id`
+    fn check(&self) -> Result<(), PortableError> {
+
+        let inconsistent = |message: String| Err(PortableError::Inconsistent(message));
+
+        let mut names: HashSet<&str> = HashSet::new();
+        for speaker in &self.speakers {
+            for name in Iterator::chain(std::iter::once(&speaker.name), speaker.aliases.iter()) {
+                if name.is_empty() {
+                    return inconsistent("a speaker has an empty name".to_string());
+                }
+                if !names.insert(name) {
+                    return inconsistent(format!("{:?} is the name of more than one speaker", name));
+                }
+            }
+        }
+
+        match self.speeches.len().checked_add(MAX_UNUSED_SPEECH_IDS) {
+            Some(max_speech_id) if self.next_speech_id <= max_speech_id => (),
+            _ => return inconsistent(format!("the next speech ID is {}, but there are only {} speeches", self.next_speech_id, self.speeches.len())),
+        }
+
+        let mut speeches: HashSet<SpeechId> = HashSet::new();
+        for speech in &self.speeches {
+            if speech.id.0 >= self.next_speech_id {
+                return inconsistent(format!("speech #{} was numbered after the next speech ID, {}", speech.id.0, self.next_speech_id));
+            }
+            if !speeches.insert(speech.id) {
+                return inconsistent(format!("there is more than one speech #{}", speech.id.0));
+            }
+            if !self.speakers.iter().any(|speaker| speaker.name == speech.speaker) {
+                return inconsistent(format!("speech #{} is by {:?}, who isn't one of the speakers", speech.id.0, speech.speaker));
+            }
+            if DateTime::parse_from_rfc3339(&speech.queued_at).is_err() {
+                return inconsistent(format!("speech #{} was queued at {:?}, which isn't a time", speech.id.0, speech.queued_at));
+            }
+        }
+
+        let order: &PortableOrder = &self.order;
+        let blocks = |blocks: &Vec<PortableBlock>| blocks.iter()
+            .flat_map(|block| Iterator::chain(std::iter::once(block.new_point), block.responses.iter().copied()))
+            .collect::<Vec<SpeechId>>();
+        let mut in_order: Vec<SpeechId> = blocks(&order.past);
+        in_order.extend(order.current_new_point);
+        in_order.extend(order.current_responses.iter().copied());
+        in_order.extend(blocks(&order.upcoming));
+
+        let mut placed: HashSet<SpeechId> = HashSet::new();
+        for id in in_order {
+            if !speeches.contains(&id) {
+                return inconsistent(format!("speech #{} is in the speaking order but isn't one of the speeches", id.0));
+            }
+            if !placed.insert(id) {
+                return inconsistent(format!("speech #{} is in the speaking order more than once", id.0));
+            }
+        }
+        if let Some(id) = speeches.difference(&placed).min() {
+            return inconsistent(format!("speech #{} isn't anywhere in the speaking order", id.0));
+        }
+
+        match (order.current_new_point, order.current_responses.is_empty()) {
+            (None, true) if !order.upcoming.is_empty() => return inconsistent("there are speeches to come but nobody is speaking".to_string()),
+            (None, false) if order.past.is_empty() => return inconsistent("a response is being given to no new point".to_string()),
+            _ => (),
+        }
+
+        for (number, motion) in self.motions.iter().enumerate() {
+            if parse_time(&motion.moved_at).is_none() {
+                return inconsistent(format!("motion #{} was moved at {:?}, which isn't a time", number, motion.moved_at));
+            }
+            match (&motion.outcome, &motion.resolved_at) {
+                (Some(_), Some(resolved_at)) if parse_time(resolved_at).is_none() => return inconsistent(format!("motion #{} was dealt with at {:?}, which isn't a time", number, resolved_at)),
+                (Some(_), Some(_)) | (None, None) => (),
+                (Some(_), None) => return inconsistent(format!("motion #{} was dealt with, but not at any time", number)),
+                (None, Some(_)) => return inconsistent(format!("motion #{} was dealt with at a time, but came to nothing", number)),
+            }
+            if motion.during_speech.is_some_and(|id| id.0 >= self.next_speech_id) {
+                return inconsistent(format!("motion #{} was moved during a speech numbered after the next speech ID", number));
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Sets the discussion up again, with its clock running (unless it is
+    ///  paused or closed). An archived discussion comes back closed.
+    pub fn into_discussion(self) -> Result<Arc<Mutex<Discussion>>, PortableError> {
+
+        self.check()?;
+
+        let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings {
+            priority_mode: self.settings.priority_mode,
+            new_point_time_limit: self.settings.new_point_time_limit.map(Duration::from_secs),
+            response_time_limit: self.settings.response_time_limit.map(Duration::from_secs),
+            max_queued_speeches_per_speaker: self.settings.max_queued_speeches_per_speaker,
+            roster: Roster::default(),
+            series: None,
+            rank_by_series: self.settings.rank_by_series,
+        });
+
+        match discussion.lock() {
+            Ok(mut locked_discussion) => {
+
+                // The speakers already carry the roster's aliases, so the roster
+                //  (and the series') is only kept for looking names up
+                locked_discussion.roster = self.settings.roster;
+                locked_discussion.series = self.settings.series;
+
+                let mut speakers: HashMap<String, Arc<Mutex<Speaker>>> = HashMap::new();
+                for portable_speaker in self.speakers {
+                    let mut speaker: Speaker = Speaker::new(portable_speaker.name.clone());
+                    speaker.aliases = portable_speaker.aliases.iter().cloned().collect();
+                    let speaker_arc: Arc<Mutex<Speaker>> = Arc::new(Mutex::new(speaker));
+                    for name in Iterator::chain(std::iter::once(portable_speaker.name), portable_speaker.aliases) {
+                        speakers.insert(name, Arc::clone(&speaker_arc));
+                    }
+                }
+
+                for speech in self.speeches {
+                    let speaker: Arc<Mutex<Speaker>> = match speakers.get(&speech.speaker) {
+                        Some(speaker) => Arc::clone(speaker),
+                        None => {
+                            debug_panic!();
+                            continue;
+                        }
+                    };
+                    locked_discussion.speeches.insert(Speech {
+                        id: speech.id,
+                        speaker: speaker,
+                        given_name: speech.given_name,
+                        queued_at: parse_time(&speech.queued_at).unwrap_or_else(Utc::now),
+                        duration: Duration::from_secs(speech.duration),
+                    });
+                }
+
+                locked_discussion.speakers = speakers;
+                locked_discussion.next_speech_id = self.next_speech_id;
+                locked_discussion.past_speeches = self.order.past.into_iter().map(unblock).collect();
+                locked_discussion.current_new_point = self.order.current_new_point;
+                locked_discussion.first_response_block = VecDeque::from(self.order.current_responses);
+                locked_discussion.upcoming_speeches = self.order.upcoming.into_iter().map(unblock).collect();
+                locked_discussion.motions = self.motions.into_iter().map(|motion| Motion {
+                    text: motion.text,
+                    moved_by: motion.moved_by,
+                    moved_at: parse_time(&motion.moved_at).unwrap_or_else(Utc::now),
+                    during_speech: motion.during_speech,
+                    outcome: motion.outcome,
+                    resolved_at: motion.resolved_at.as_deref().and_then(parse_time),
+                }).collect();
+                locked_discussion.paused = self.paused;
+                locked_discussion.duration = Duration::from_secs(self.duration);
+                locked_discussion.lifecycle = match self.lifecycle {
+                    Lifecycle::Open => Lifecycle::Open,
+                    Lifecycle::Closed | Lifecycle::Archived => Lifecycle::Closed,
+                };
+                locked_discussion.recount_speakers();
+                locked_discussion.history.clear();
+                // Whatever the discussion looked like before it moved has
+                //  already been reported from wherever it was
+                locked_discussion.take_events();
+            },
+            Err(e) => return Err(PortableError::Inconsistent(e.to_string())),
+        }
+
+        return Ok(discussion);
+    }
+
+}
+
+// Brings a saved discussion up to `FORMAT_VERSION` one version at a time. Each
+//  step only knows about the version it starts from and the one after it, so
+//  the steps never need revisiting when the format moves on again.
+fn migrate(mut value: Value) -> Result<Value, PortableError> {
+    loop {
+        let version: u64 = match value.get("version") {
+            Some(version) => match version.as_u64() {
+                Some(version) => version,
+                None => return Err(PortableError::Malformed(format!("{} isn't a format version", version))),
+            },
+            None => 0,
+        };
+        value = match version {
+            0 => from_archive(value)?,
+            FORMAT_VERSION => return Ok(value),
+            _ => return Err(PortableError::TooNew(version)),
+        };
+    }
+}
+
+#[derive(Deserialize)]
+struct ArchivedSpeech {
+    id: usize,
+    speaker: String,
+    stype: u8,
+    queued_at: String,
+    duration: u64,
+    position: String,
+}
+
+#[derive(Deserialize)]
+struct Archive {
+    archived_at: String,
+    lifecycle: Value,
+    priority_mode: Value,
+    duration: u64,
+    speakers: Vec<Value>,
+    speeches: Vec<ArchivedSpeech>,
+    // Archives from before motions were recorded don't list them
+    #[serde(default)]
+    motions: Vec<Value>,
+}
+
+// Version 0 to 1. An archive lists the speeches in speaking order, each marked
+//  as past, current or upcoming, which is enough to rebuild the blocks: every
+//  new point starts a block, and responses still to come before the first
+//  upcoming new point belong to the current one.
+fn from_archive(value: Value) -> Result<Value, PortableError> {
+
+    let archive: Archive = match serde_json::from_value(value) {
+        Ok(archive) => archive,
+        Err(e) => return Err(PortableError::Malformed(e.to_string())),
+    };
+
+    let mut past: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut current_new_point: Option<usize> = None;
+    let mut current_responses: Vec<usize> = Vec::new();
+    let mut upcoming: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut next_speech_id: usize = 0;
+    for speech in &archive.speeches {
+        next_speech_id = match speech.id.checked_add(1) {
+            Some(after) => usize::max(next_speech_id, after),
+            None => return Err(PortableError::Inconsistent(format!("speech #{} is numbered past the last possible speech ID", speech.id))),
+        };
+        let is_response: bool = match speech.stype {
+            1 => false,
+            2 => true,
+            stype => return Err(PortableError::Malformed(format!("speech #{} has speech type {}", speech.id, stype))),
+        };
+        let block: Option<&mut Vec<usize>> = match (speech.position.as_str(), is_response) {
+            ("Past", false) => {
+                past.push((speech.id, Vec::new()));
+                None
+            },
+            ("Past", true) => past.last_mut().map(|(_, responses)| responses),
+            ("Current", false) => {
+                current_new_point = Some(speech.id);
+                None
+            },
+            ("Current", true) => Some(&mut current_responses),
+            ("Upcoming", false) => {
+                upcoming.push((speech.id, Vec::new()));
+                None
+            },
+            ("Upcoming", true) => match upcoming.last_mut() {
+                Some((_, responses)) => Some(responses),
+                None => Some(&mut current_responses),
+            },
+            (position, _) => return Err(PortableError::Malformed(format!("speech #{} is in position {:?}", speech.id, position))),
+        };
+        match (block, is_response) {
+            (Some(block), true) => block.push(speech.id),
+            (None, true) => return Err(PortableError::Inconsistent(format!("response #{} comes before any new point", speech.id))),
+            (_, false) => (),
+        }
+    }
+
+    let blocks = |blocks: Vec<(usize, Vec<usize>)>| blocks.into_iter()
+        .map(|(new_point, responses)| json!({ "new_point": new_point, "responses": responses }))
+        .collect::<Vec<Value>>();
+
+    return Ok(json!({
+        "version": 1,
+        "exported_at": archive.archived_at,
+        "settings": {
+            "priority_mode": archive.priority_mode,
+            "new_point_time_limit": null,
+            "response_time_limit": null,
+            "max_queued_speeches_per_speaker": null,
+        },
+        "lifecycle": archive.lifecycle,
+        "paused": false,
+        "duration": archive.duration,
+        "next_speech_id": next_speech_id,
+        "speakers": archive.speakers.iter().map(|speaker| json!({ "name": speaker["name"], "aliases": speaker["aliases"] })).collect::<Vec<Value>>(),
+        "speeches": archive.speeches.iter().map(|speech| json!({
+            "id": speech.id,
+            "speaker": speech.speaker,
+            "given_name": speech.speaker,
+            "queued_at": speech.queued_at,
+            "duration": speech.duration,
+        })).collect::<Vec<Value>>(),
+        "order": {
+            "past": blocks(past),
+            "current_new_point": current_new_point,
+            "current_responses": current_responses,
+            "upcoming": blocks(upcoming),
+        },
+        "motions": archive.motions,
+    }));
+}
+
+#[cfg(test)]
+fn example_discussion() -> Arc<Mutex<Discussion>> {
+    let discussion: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings {
+        priority_mode: PriorityMode::FavourBriefest,
+        new_point_time_limit: Some(Duration::from_secs(120)),
+        ..DiscussionSettings::default()
+    });
+    {
+        let mut discussion = discussion.lock().unwrap();
+        discussion.add_new_speech("Ada".to_string(), false);
+        discussion.add_new_speech("Alan".to_string(), true);
+        discussion.add_new_speech("Bea".to_string(), true);
+        discussion.add_new_speech("Carol".to_string(), false);
+        discussion.add_new_speech("Dee".to_string(), true);
+        discussion.tick_clock();
+        discussion.goto_next_speech();
+        discussion.tick_clock();
+        discussion.alias_speakers(&"Bea".to_string(), &"Beatrice".to_string());
+        discussion.move_motion("That the report be adopted".to_string(), "Alan".to_string());
+        discussion.resolve_motion(0, MotionOutcome::Carried);
+        discussion.move_motion("That we adjourn".to_string(), "Dee".to_string());
+        discussion.set_paused(true);
+    }
+    return discussion;
+}
+
+#[test]
+fn test_round_trip() {
+    let discussion: Arc<Mutex<Discussion>> = example_discussion();
+    let exported: PortableDiscussion = PortableDiscussion::new(&discussion.lock().unwrap());
+    let json: String = serde_json::to_string(&exported).unwrap();
+
+    let imported: Arc<Mutex<Discussion>> = PortableDiscussion::from_json(&json).unwrap().into_discussion().unwrap();
+    let imported = imported.lock().unwrap();
+    let original = discussion.lock().unwrap();
+    assert_eq!(imported.speeches_in_order(), original.speeches_in_order());
+    assert_eq!(imported.current_speech(), original.current_speech());
+    assert!(imported.paused);
+    assert_eq!(imported.duration, Duration::from_secs(2));
+    assert_eq!(imported.new_point_time_limit, Some(Duration::from_secs(120)));
+    assert_eq!(imported.get_priority_mode(), &PriorityMode::FavourBriefest);
+    assert!(Arc::ptr_eq(&imported.speakers["Bea"], &imported.speakers["Beatrice"]));
+    assert_eq!(imported.motions, original.motions);
+    assert_eq!(imported.check_speaker_totals(), Ok(()));
+
+    // Exporting it again gives the same file, bar the time it was exported
+    let mut exported_again: PortableDiscussion = PortableDiscussion::new(&imported);
+    exported_again.exported_at = exported.exported_at.clone();
+    assert_eq!(exported_again, exported);
+}
+
+#[test]
+fn test_archives_are_migrated() {
+    let discussion: Arc<Mutex<Discussion>> = example_discussion();
+    let original = discussion.lock().unwrap();
+    let archive: String = serde_json::to_string(&crate::storage::DiscussionRecord::new("board", &original)).unwrap();
+
+    let imported: Arc<Mutex<Discussion>> = PortableDiscussion::from_json(&archive).unwrap().into_discussion().unwrap();
+    let imported = imported.lock().unwrap();
+    assert_eq!(imported.speeches_in_order(), original.speeches_in_order());
+    assert_eq!(imported.past_speeches, original.past_speeches);
+    assert_eq!(imported.first_response_block, original.first_response_block);
+    assert_eq!(imported.upcoming_speeches, original.upcoming_speeches);
+    assert!(Arc::ptr_eq(&imported.speakers["Bea"], &imported.speakers["Beatrice"]));
+    assert_eq!(imported.motions, original.motions);
+}
+
+#[test]
+fn test_bad_files_are_refused() {
+    let discussion: Arc<Mutex<Discussion>> = example_discussion();
+    let exported: PortableDiscussion = PortableDiscussion::new(&discussion.lock().unwrap());
+
+    let mut too_new: Value = serde_json::to_value(&exported).unwrap();
+    too_new["version"] = json!(FORMAT_VERSION + 1);
+    assert_eq!(PortableDiscussion::from_json(&too_new.to_string()), Err(PortableError::TooNew(FORMAT_VERSION + 1)));
+
+    let mut lost_speech: PortableDiscussion = exported.clone();
+    lost_speech.order.upcoming.clear();
+    assert!(matches!(lost_speech.into_discussion(), Err(PortableError::Inconsistent(_))));
+
+    let mut undated_motion: PortableDiscussion = exported.clone();
+    undated_motion.motions[0].resolved_at = None;
+    assert!(matches!(undated_motion.into_discussion(), Err(PortableError::Inconsistent(_))));
+
+    let mut unknown_speaker: PortableDiscussion = exported.clone();
+    unknown_speaker.speeches[0].speaker = "Zed".to_string();
+    assert!(matches!(unknown_speaker.into_discussion(), Err(PortableError::Inconsistent(_))));
+
+    // Speech IDs decide how much room the speeches take up, so they can't be
+    //  made up
+    let renumber = |portable: &mut PortableDiscussion, from: SpeechId, to: SpeechId| {
+        let renumber_one = |id: &mut SpeechId| if *id == from {
+            *id = to;
+        };
+        portable.speeches.iter_mut().for_each(|speech| renumber_one(&mut speech.id));
+        for block in portable.order.past.iter_mut().chain(portable.order.upcoming.iter_mut()) {
+            renumber_one(&mut block.new_point);
+            block.responses.iter_mut().for_each(renumber_one);
+        }
+        portable.order.current_new_point.iter_mut().for_each(renumber_one);
+        portable.order.current_responses.iter_mut().for_each(renumber_one);
+    };
+    for id in [exported.next_speech_id, 1_000_000_000_000_000_000, usize::MAX] {
+        let mut huge_id: PortableDiscussion = exported.clone();
+        renumber(&mut huge_id, exported.speeches[0].id, SpeechId(id));
+        assert!(matches!(huge_id.into_discussion(), Err(PortableError::Inconsistent(e)) if e.contains("after the next speech ID")), "{}", id);
+    }
+    for next_speech_id in [exported.speeches.len() + MAX_UNUSED_SPEECH_IDS + 1, usize::MAX] {
+        let mut huge_next_id: PortableDiscussion = exported.clone();
+        huge_next_id.next_speech_id = next_speech_id;
+        assert!(matches!(huge_next_id.into_discussion(), Err(PortableError::Inconsistent(e)) if e.starts_with("the next speech ID")), "{}", next_speech_id);
+    }
+    let mut withdrawn: PortableDiscussion = exported.clone();
+    withdrawn.next_speech_id = exported.speeches.len() + MAX_UNUSED_SPEECH_IDS;
+    renumber(&mut withdrawn, exported.speeches[0].id, SpeechId(exported.speeches.len() + MAX_UNUSED_SPEECH_IDS - 1));
+    assert_eq!(withdrawn.into_discussion().map(|discussion| discussion.lock().unwrap().next_speech_id), Ok(exported.speeches.len() + MAX_UNUSED_SPEECH_IDS));
+    let mut huge_archived_id: Value = serde_json::to_value(crate::storage::DiscussionRecord::new("board", &discussion.lock().unwrap())).unwrap();
+    huge_archived_id["speeches"][0]["id"] = json!(usize::MAX);
+    assert!(matches!(PortableDiscussion::from_json(&huge_archived_id.to_string()), Err(PortableError::Inconsistent(_))));
+
+    assert!(matches!(PortableDiscussion::from_json("[]"), Err(PortableError::Malformed(_))));
+}
@@ -0,0 +1,287 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+/// Someone we know will be at the meeting. `voting` is left as `None` when the
+///  roster doesn't say either way.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RosterEntry {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub voting: Option<bool>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// The people expected at a discussion and the other names they might be
+///  typed in under, loaded from JSON or CSV
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roster {
+    pub entries: Vec<RosterEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RosterError {
+    Malformed(String),
+    EmptyName(usize),
+    BadVotingValue(usize, String),
+    DuplicateName(String),
+}
+
+impl fmt::Display for RosterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RosterError::Malformed(e) => write!(f, "the roster could not be read: {}", e),
+            RosterError::EmptyName(n) => write!(f, "roster entry {} has no name", n),
+            RosterError::BadVotingValue(n, value) => write!(f, "roster entry {} has {:?} for voting (expected yes/no, true/false or blank)", n, value),
+            RosterError::DuplicateName(name) => write!(f, "{:?} appears in the roster more than once", name),
+        }
+    }
+}
+
+// Names are compared without regard to case or spacing
+fn simplify(name: &str) -> String {
+    return name.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase();
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row: Vec<usize> = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution: usize = previous_row[j] + if a_char == *b_char { 0 } else { 1 };
+            row.push(*[substitution, previous_row[j + 1] + 1, row[j] + 1].iter().min().unwrap_or(&0));
+        }
+        previous_row = row;
+    }
+    return previous_row[b.len()];
+}
+
+// How many typos we are willing to look past. Short names get less leeway,
+//  since "Al" and "Ali" are more likely different people than a typo
+fn typo_allowance(name: &str) -> usize {
+    match name.chars().count() {
+        0..=3 => return 0,
+        4..=7 => return 1,
+        _ => return 2,
+    }
+}
+
+impl Roster {
+
+    /// Reads a JSON array of entries, e.g.
+    ///  `[{"name": "Ada Lovelace", "aliases": ["Ada"], "voting": true, "tags": ["exec"]}]`
+    pub fn from_json(json: &str) -> Result<Self, RosterError> {
+        let entries: Vec<RosterEntry> = match serde_json::from_str(json) {
+            Ok(entries) => entries,
+            Err(e) => return Err(RosterError::Malformed(e.to_string())),
+        };
+        return Self::new(entries);
+    }
+
+    /// Reads CSV with a header row. Only the `name` column is required; the
+    ///  `aliases` and `tags` columns hold lists separated by semicolons and the
+    ///  `voting` column holds yes/no (or true/false), e.g.
+    ///
+    /// ```text
+    /// name,aliases,voting,tags
+    /// Ada Lovelace,Ada;Countess,yes,exec;treasurer
+    /// ```
+    pub fn from_csv(csv_text: &str) -> Result<Self, RosterError> {
+
+        let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(csv_text.as_bytes());
+
+        let headers: Vec<String> = match reader.headers() {
+            Ok(headers) => headers.iter().map(|header| header.to_lowercase()).collect(),
+            Err(e) => return Err(RosterError::Malformed(e.to_string())),
+        };
+        let column = |name: &str| headers.iter().position(|header| header == name);
+        let name_column: usize = match column("name") {
+            Some(i) => i,
+            None => return Err(RosterError::Malformed("there is no `name` column".to_string())),
+        };
+        let (aliases_column, voting_column, tags_column) = (column("aliases"), column("voting"), column("tags"));
+
+        let list = |field: Option<&str>| -> Vec<String> {
+            return field.unwrap_or("").split(';').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect();
+        };
+
+        let mut entries: Vec<RosterEntry> = Vec::new();
+        for (i, record) in reader.records().enumerate() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => return Err(RosterError::Malformed(e.to_string())),
+            };
+            let voting: Option<bool> = match voting_column.and_then(|c| record.get(c)).map(str::to_lowercase).as_deref() {
+                None | Some("") => None,
+                Some("yes") | Some("y") | Some("true") => Some(true),
+                Some("no") | Some("n") | Some("false") => Some(false),
+                Some(other) => return Err(RosterError::BadVotingValue(i + 1, other.to_string())),
+            };
+            entries.push(RosterEntry {
+                name: record.get(name_column).unwrap_or("").to_string(),
+                aliases: list(aliases_column.and_then(|c| record.get(c))),
+                voting: voting,
+                tags: list(tags_column.and_then(|c| record.get(c))),
+            });
+        }
+
+        return Self::new(entries);
+    }
+
+    /// Checks that every entry has a name and that no name (or alias) could
+    ///  refer to two different people
+    pub fn new(entries: Vec<RosterEntry>) -> Result<Self, RosterError> {
+        let mut seen: HashSet<String> = HashSet::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.name.trim().is_empty() {
+                return Err(RosterError::EmptyName(i + 1));
+            }
+            for name in Iterator::chain(std::iter::once(&entry.name), entry.aliases.iter()) {
+                if !seen.insert(simplify(name)) {
+                    return Err(RosterError::DuplicateName(name.clone()));
+                }
+            }
+        }
+        return Ok(Roster { entries: entries });
+    }
+
+    /// Adds everyone on `other` who isn't on this roster already. Someone on
+    ///  both keeps the entry they have here, along with any names `other` has
+    ///  for them which nobody here goes by. Names which belong to somebody else
+    ///  here are dropped, so no name can end up referring to two people.
+    pub fn merge(&mut self, other: Roster) {
+        let mut taken: HashSet<String> = self.entries.iter()
+            .flat_map(|entry| Iterator::chain(std::iter::once(&entry.name), entry.aliases.iter()))
+            .map(|name| simplify(name))
+            .collect();
+        for mut entry in other.entries {
+            let existing: Option<usize> = self.entries.iter().position(|own| {
+                return Iterator::chain(std::iter::once(&own.name), own.aliases.iter()).any(|name| simplify(name) == simplify(&entry.name));
+            });
+            let free_names: Vec<String> = Iterator::chain(std::iter::once(entry.name.clone()), entry.aliases.drain(..))
+                .filter(|name| taken.insert(simplify(name)))
+                .collect();
+            match existing {
+                Some(i) => self.entries[i].aliases.extend(free_names),
+                None => {
+                    entry.aliases = free_names.into_iter().skip(1).collect();
+                    self.entries.push(entry);
+                },
+            }
+        }
+    }
+
+    /// Finds the member someone most likely meant by `typed`. Exact names and
+    ///  aliases win, ignoring case and spacing. Failing that, a name or alias
+    ///  within a typo or two is accepted, but only if no other member is just
+    ///  as close.
+    pub fn find(&self, typed: &str) -> Option<&RosterEntry> {
+
+        let typed: String = simplify(typed);
+
+        let names = || self.entries.iter().flat_map(|entry| {
+            Iterator::chain(std::iter::once(&entry.name), entry.aliases.iter()).map(move |name| (entry, simplify(name)))
+        });
+
+        if let Some((entry, _)) = names().find(|(_, name)| *name == typed) {
+            return Some(entry);
+        }
+
+        let mut best: Option<(&RosterEntry, usize)> = None;
+        let mut is_ambiguous: bool = false;
+        for (entry, name) in names() {
+            let distance: usize = edit_distance(&typed, &name);
+            if distance > typo_allowance(&name) {
+                continue;
+            }
+            match best {
+                Some((best_entry, best_distance)) if distance == best_distance => {
+                    if !std::ptr::eq(best_entry, entry) {
+                        is_ambiguous = true;
+                    }
+                },
+                Some((_, best_distance)) if distance > best_distance => (),
+                _ => {
+                    best = Some((entry, distance));
+                    is_ambiguous = false;
+                },
+            }
+        }
+
+        if is_ambiguous {
+            return None;
+        } else {
+            return best.map(|(entry, _)| entry);
+        }
+    }
+
+}
+
+#[cfg(test)]
+fn example_roster() -> Roster {
+    return Roster::from_csv("name,aliases,voting,tags\nAda Lovelace,Ada;Countess,yes,exec;treasurer\nAlan Turing,,no,\nGrace Hopper,Amazing Grace,,exec\n").unwrap();
+}
+
+#[test]
+fn test_parse_csv() {
+    let roster: Roster = example_roster();
+    assert_eq!(roster.entries.len(), 3);
+    assert_eq!(roster.entries[0], RosterEntry {
+        name: "Ada Lovelace".to_string(),
+        aliases: vec!["Ada".to_string(), "Countess".to_string()],
+        voting: Some(true),
+        tags: vec!["exec".to_string(), "treasurer".to_string()],
+    });
+    assert_eq!(roster.entries[1].voting, Some(false));
+    assert_eq!(roster.entries[2].voting, None);
+
+    assert_eq!(Roster::from_csv("name,tags\nAda,\n,exec\n"), Err(RosterError::EmptyName(2)));
+    assert_eq!(Roster::from_csv("name,voting\nAda,maybe\n"), Err(RosterError::BadVotingValue(1, "maybe".to_string())));
+    assert_eq!(Roster::from_csv("name,aliases\nAda,\nAda Lovelace,ada\n"), Err(RosterError::DuplicateName("ada".to_string())));
+    assert!(matches!(Roster::from_csv("who\nAda\n"), Err(RosterError::Malformed(_))));
+}
+
+#[test]
+fn test_parse_json() {
+    let roster: Roster = Roster::from_json(r#"[{"name": "Ada Lovelace", "aliases": ["Ada"], "voting": true}, {"name": "Alan Turing"}]"#).unwrap();
+    assert_eq!(roster.entries[0].aliases, vec!["Ada".to_string()]);
+    assert_eq!(roster.entries[1].voting, None);
+    assert!(matches!(Roster::from_json(r#"{"name": "Ada"}"#), Err(RosterError::Malformed(_))));
+}
+
+#[test]
+fn test_merge() {
+    let mut roster: Roster = Roster::from_csv("name,aliases\nAda Lovelace,Ada\nBob,\n").unwrap();
+    roster.merge(Roster::from_csv("name,aliases\nada lovelace,Countess\nAlan Turing,Bob;Prof\n").unwrap());
+    assert_eq!(roster.entries.iter().map(|entry| entry.name.as_str()).collect::<Vec<&str>>(), vec!["Ada Lovelace", "Bob", "Alan Turing"]);
+    assert_eq!(roster.entries[0].aliases, vec!["Ada".to_string(), "Countess".to_string()]);
+    // "Bob" is already somebody else
+    assert_eq!(roster.entries[2].aliases, vec!["Prof".to_string()]);
+    assert!(Roster::new(roster.entries.clone()).is_ok());
+}
+
+#[test]
+fn test_find() {
+    let roster: Roster = example_roster();
+    let find = |typed: &str| roster.find(typed).map(|entry| entry.name.as_str());
+    assert_eq!(find("Ada Lovelace"), Some("Ada Lovelace"));
+    assert_eq!(find("  ada   LOVELACE "), Some("Ada Lovelace"));
+    assert_eq!(find("countess"), Some("Ada Lovelace"));
+    assert_eq!(find("Ada Lovelance"), Some("Ada Lovelace"));
+    assert_eq!(find("Alan Turnig"), Some("Alan Turing"));
+    assert_eq!(find("Amazing Grase"), Some("Grace Hopper"));
+    assert_eq!(find("Adam"), None);
+    assert_eq!(find("Charles Babbage"), None);
+}
+
+#[test]
+fn test_find_refuses_ambiguous_typos() {
+    let roster: Roster = Roster::from_csv("name\nJon Smith\nJan Smith\n").unwrap();
+    assert_eq!(roster.find("Jin Smith"), None);
+    assert_eq!(roster.find("Jon Smyth").map(|entry| entry.name.as_str()), Some("Jon Smith"));
+}
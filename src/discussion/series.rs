@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use debug_panic::debug_panic;
+use serde::{Serialize, Deserialize};
+
+use super::Discussion;
+use super::roster::Roster;
+use super::speech::Speaker;
+
+/// What one person did across the meetings of a series settled so far.
+///  `meetings` only counts the meetings they spoke in, and `speaking_time` is
+///  in whole seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeriesTotals {
+    pub meetings: u32,
+    pub speeches: u32,
+    pub speaking_time: u64,
+}
+
+/// A run of meetings of the same group, e.g. a committee meeting every week.
+///  Every meeting in a series starts out with its roster, and once a meeting is
+///  over its speakers' totals are added to the series' (see `settle`), so that
+///  fairness can be judged across the series and not just one meeting.
+///
+/// People are matched up between meetings by the name the roster knows them
+///  by, or by the name they spoke under if they aren't on it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Series {
+    pub name: String,
+    #[serde(default)]
+    pub roster: Roster,
+    #[serde(default)]
+    pub meetings: u32,
+    #[serde(default)]
+    pub totals: BTreeMap<String, SeriesTotals>,
+}
+
+impl Series {
+
+    pub fn new(name: String, roster: Roster) -> Self {
+        Series {
+            name: name,
+            roster: roster,
+            meetings: 0,
+            totals: BTreeMap::new(),
+        }
+    }
+
+    // The name a speaker's totals are kept under
+    fn series_name(&self, name: &str) -> String {
+        if self.totals.contains_key(name) {
+            return name.to_string();
+        }
+        match self.roster.find(name) {
+            Some(entry) => return entry.name.clone(),
+            None => return name.to_string(),
+        }
+    }
+
+    /// What a speaker did in the meetings settled so far, under any of the
+    ///  names they go by
+    pub fn totals_of(&self, speaker: &Speaker) -> SeriesTotals {
+        let names: BTreeSet<String> = std::iter::once(&speaker.name).chain(speaker.aliases.iter())
+            .map(|name| self.series_name(name))
+            .collect();
+        let mut totals: SeriesTotals = SeriesTotals::default();
+        for earlier in names.iter().filter_map(|name| self.totals.get(name)) {
+            totals.meetings = u32::max(totals.meetings, earlier.meetings);
+            totals.speeches += earlier.speeches;
+            totals.speaking_time += earlier.speaking_time;
+        }
+        return totals;
+    }
+
+    /// Adds a meeting's speakers' totals to the series'. Each meeting should
+    ///  only be settled once, when it is over.
+    pub fn settle(&mut self, discussion: &Discussion) {
+
+        self.meetings += 1;
+
+        // Every name a speaker goes by shares the one `Arc`
+        let mut seen: Vec<&Arc<Mutex<Speaker>>> = Vec::new();
+        for speaker_arc in discussion.speakers.values() {
+            if seen.iter().any(|s| Arc::ptr_eq(s, speaker_arc)) {
+                continue;
+            }
+            seen.push(speaker_arc);
+            let speaker = match speaker_arc.lock() {
+                Ok(speaker) => speaker,
+                Err(e) => {
+                    debug_panic!(e.to_string());
+                    continue;
+                }
+            };
+            // Everyone on the roster has a speaker, whether or not they came
+            if speaker.number_of_speeches_given == 0 && speaker.total_speaking_time.is_zero() {
+                continue;
+            }
+            let totals: &mut SeriesTotals = self.totals.entry(self.series_name(&speaker.name)).or_default();
+            totals.meetings += 1;
+            totals.speeches += speaker.number_of_speeches_given as u32;
+            totals.speaking_time += speaker.total_speaking_time.as_secs();
+        }
+
+    }
+
+}
+
+#[test]
+fn test_series_totals_carry_across_meetings() {
+    use super::{DiscussionSettings, PriorityMode};
+
+    let roster: Roster = Roster::from_csv("name,aliases\nAda Lovelace,Ada\nAlan Turing,Alan\n").unwrap();
+    let mut series: Series = Series::new("committee".to_string(), roster);
+
+    // Ada holds forth at the first meeting, and Alan says a little
+    let first = Discussion::new(DiscussionSettings { series: Some(series.clone()), ..DiscussionSettings::default() });
+    let mut first = first.lock().unwrap();
+    first.add_new_speech("Ada".to_string(), false);
+    first.add_new_speech("Alan".to_string(), false);
+    for _ in 0..5 {
+        first.tick_clock();
+    }
+    first.goto_next_speech();
+    first.tick_clock();
+    first.goto_next_speech();
+    first.close();
+    series.settle(&first);
+    assert_eq!(series.meetings, 1);
+    assert_eq!(series.totals["Ada Lovelace"], SeriesTotals { meetings: 1, speeches: 1, speaking_time: 5 });
+    assert_eq!(series.totals["Alan Turing"], SeriesTotals { meetings: 1, speeches: 1, speaking_time: 1 });
+
+    // At the next meeting both start afresh, so only the series remembers who
+    //  has had more than their share
+    let second = Discussion::new(DiscussionSettings {
+        priority_mode: PriorityMode::FavourBriefest,
+        series: Some(series.clone()),
+        ..DiscussionSettings::default()
+    });
+    let mut second = second.lock().unwrap();
+    second.add_new_speech("Grace".to_string(), false);
+    second.add_new_speech("ada lovelace".to_string(), false);
+    second.add_new_speech("Alan".to_string(), false);
+    let upcoming = |discussion: &Discussion| -> Vec<String> {
+        return discussion.upcoming_speeches.iter()
+            .map(|(new_point, _)| discussion.speeches.get(*new_point).unwrap().speaker.lock().unwrap().name.clone())
+            .collect();
+    };
+    assert_eq!(upcoming(&second), vec!["Ada Lovelace", "Alan Turing"]);
+    second.set_rank_by_series(true);
+    assert_eq!(upcoming(&second), vec!["Alan Turing", "Ada Lovelace"]);
+
+    // Counted in speeches they are level, so they stay as they were
+    second.set_priority_mode(PriorityMode::FavourShiest);
+    assert_eq!(upcoming(&second), vec!["Alan Turing", "Ada Lovelace"]);
+
+    second.goto_next_speech();
+    second.tick_clock();
+    second.close();
+    series.settle(&second);
+    assert_eq!(series.meetings, 2);
+    assert_eq!(series.totals["Ada Lovelace"], SeriesTotals { meetings: 1, speeches: 1, speaking_time: 5 });
+    assert_eq!(series.totals["Alan Turing"], SeriesTotals { meetings: 2, speeches: 2, speaking_time: 2 });
+    assert_eq!(series.totals["Grace"], SeriesTotals { meetings: 1, speeches: 1, speaking_time: 0 });
+}
@@ -1,11 +1,17 @@
-use debug_panic::debug_panic;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::collections::HashSet;
 
+use chrono::prelude::*;
+use debug_panic::debug_panic;
+use serde::{Serialize, Deserialize};
+
 const ZERO_SECONDS: Duration = Duration::from_secs(0);
 const ONE_SECOND: Duration = Duration::from_secs(1);
 
+/// A speaker's totals are never counted up directly. They are a cache of what
+///  the speaker's speeches add up to, which `Discussion::recount_speakers`
+///  refreshes whenever the speeches change.
 #[derive(Debug, Clone)]
 pub struct Speaker {
     pub name: String,
@@ -25,51 +31,100 @@ impl Speaker {
         }
     }
 
+    /// Takes on all of `other`'s names. Totals aren't carried over, since they
+    ///  are recounted once `other`'s speeches have been handed over
     pub fn merge_with(&mut self, other: Self) {
         for alias in other.aliases {
             self.aliases.insert(alias);
         }
         self.aliases.insert(other.name);
-        self.total_speaking_time += other.total_speaking_time;
-        self.number_of_speeches_given += other.number_of_speeches_given;
-    }
-    
-    /*
-    pub fn has_the_same_name_as(&self, other: Self) -> Option<&String> {
-
-        if self.name == other.name || other.aliases.contains(&self.name) {
-            return Some(&self.name);
-        }
-
-        for alias in &self.aliases {
-            if *alias == other.name || other.aliases.contains(alias) {
-                return Some(alias);
-            }
-        }
-
-        return None;
-    }
-    */
-
-    fn tick_speaking_time(&mut self) {
-        self.total_speaking_time += ONE_SECOND;
     }
 
 }
 
-#[derive(Debug)]
+/// `given_name` is the name the speech was added under, which is remembered so
+///  that a speech can find its way back to the right person if their name is
+///  later split off from a speaker it was wrongly aliased to.
+///
+/// `id` and `queued_at` are handed out by the discussion when the speech is
+///  added and never change afterwards.
+#[derive(Debug, Clone)]
 pub struct Speech {
+    pub id: SpeechId,
     pub speaker: Arc<Mutex<Speaker>>,
+    pub given_name: String,
+    pub queued_at: DateTime<Utc>,
     pub duration: Duration,
-    pub fcfs_order: usize,
 }
 
 impl Speech {
     pub fn tick_clock(&mut self) {
         self.duration += ONE_SECOND;
-        match self.speaker.lock() {
-            Ok(mut speaker_locked) => speaker_locked.tick_speaking_time(),
-            Err(e) => debug_panic!(e.to_string()),
+    }
+}
+
+/// IDs count up from zero in the order speeches are added to a discussion and
+///  are never reused, not even once a speech has been withdrawn. That makes
+///  them both the first come first serve order and a handle clients can hold on
+///  to while the speaking order changes around them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SpeechId(pub usize);
+
+/// Every speech in a discussion, wherever it is in the speaking order. The
+///  speaking order itself is made of `SpeechId`s, so reordering never moves a
+///  speech and any speech can be looked up directly. IDs are handed out one
+///  after another, so each speech sits at the index of its own ID.
+#[derive(Debug, Clone, Default)]
+pub struct SpeechStore {
+    slots: Vec<Option<Speech>>,
+}
+
+impl SpeechStore {
+
+    pub fn insert(&mut self, speech: Speech) -> SpeechId {
+        let id: SpeechId = speech.id;
+        if self.slots.len() <= id.0 {
+            self.slots.resize_with(id.0 + 1, || None);
+        }
+        if self.slots[id.0].replace(speech).is_some() {
+            debug_panic!("speech IDs must be unique");
         }
+        return id;
+    }
+
+    pub fn get(&self, id: SpeechId) -> Option<&Speech> {
+        return self.slots.get(id.0).and_then(Option::as_ref);
+    }
+
+    pub fn get_mut(&mut self, id: SpeechId) -> Option<&mut Speech> {
+        return self.slots.get_mut(id.0).and_then(Option::as_mut);
+    }
+
+    pub fn remove(&mut self, id: SpeechId) -> Option<Speech> {
+        return self.slots.get_mut(id.0).and_then(Option::take);
     }
-}
\ No newline at end of file
+
+    /// In order of ID
+    pub fn iter(&self) -> impl Iterator<Item = &Speech> {
+        return self.slots.iter().filter_map(Option::as_ref);
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Speech> {
+        return self.slots.iter_mut().filter_map(Option::as_mut);
+    }
+
+}
+
+#[test]
+fn test_speech_ids_are_never_reused() {
+    let speaker: Arc<Mutex<Speaker>> = Arc::new(Mutex::new(Speaker::new("Alice".to_string())));
+    let speech = |id: usize| Speech { id: SpeechId(id), speaker: Arc::clone(&speaker), given_name: "Alice".to_string(), queued_at: Utc::now(), duration: ZERO_SECONDS };
+    let mut store: SpeechStore = SpeechStore::default();
+    let first: SpeechId = store.insert(speech(0));
+    assert_eq!(store.remove(first).map(|speech| speech.id), Some(first));
+    assert!(store.get(first).is_none());
+    assert!(store.remove(first).is_none());
+    let second: SpeechId = store.insert(speech(1));
+    assert_ne!(first, second);
+    assert_eq!(store.get(second).map(|speech| speech.id), Some(second));
+}
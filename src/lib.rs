@@ -0,0 +1,37 @@
+//! Keeps track of a Robert's rules speaking order.
+//!
+//! The engine is in [`discussion`]: a [`Discussion`](discussion::Discussion)
+//! holds the speeches given and waiting, puts new points and responses in the
+//! right order under the chosen [`PriorityMode`](discussion::PriorityMode), and
+//! times whoever is speaking. [`storage`] turns a discussion into the record
+//! which gets archived or exported, and [`chat`] drives a discussion from chat
+//! commands. None of these depend on the web server, which is in `server`
+//! behind the `server` feature (on by default).
+//!
+//! ```
+//! use speakeasy::discussion::{Discussion, DiscussionSettings};
+//! use speakeasy::storage::DiscussionRecord;
+//!
+//! let discussion = Discussion::new(DiscussionSettings::default());
+//! let mut discussion = discussion.lock().unwrap();
+//! discussion.add_new_speech("Ada".to_string(), false);
+//! discussion.add_new_speech("Alan".to_string(), true);
+//!
+//! // Ada's new point is under way, and Alan's response comes next
+//! let current = discussion.current_speech().unwrap();
+//! assert_eq!(discussion.speeches.get(current).unwrap().speaker.lock().unwrap().name, "Ada");
+//! discussion.goto_next_speech();
+//!
+//! let record = DiscussionRecord::new("board", &discussion);
+//! assert_eq!(record.speeches.len(), 2);
+//! ```
+
+#[cfg(feature = "server")]
+#[macro_use] extern crate rocket;
+
+pub mod discussion;
+pub mod storage;
+pub mod format_duration;
+pub mod chat;
+#[cfg(feature = "server")]
+pub mod server;
@@ -0,0 +1,52 @@
+use rocket::request::{self, FromRequest, Request};
+use rocket::outcome::Outcome;
+use rocket::http::Status as HttpStatus;
+
+use super::config::Config;
+use super::messages::ApiError;
+
+// A request guard for the admin API. Operators (and `speakeasy-admin`) send the
+//  configured `admin_token` as a bearer token; whoever has it can also do
+//  anything a chair can in every discussion (see `permissions::Allowed`).
+pub struct Admin;
+
+// Takes as long to say no to a token that is almost right as to one which is
+//  completely wrong, so the token can't be guessed a character at a time
+pub fn tokens_match(given: &[u8], expected: &[u8]) -> bool {
+    if given.len() != expected.len() {
+        return false;
+    }
+    return given.iter().zip(expected).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0;
+}
+
+pub fn is_admin(request: &Request<'_>) -> bool {
+    let expected: &str = match request.rocket().state::<Config>().and_then(|config| config.admin_token.as_deref()) {
+        Some(token) => token,
+        None => return false,
+    };
+    match request.headers().get_one("Authorization").and_then(|header| header.strip_prefix("Bearer ")) {
+        Some(given) => return tokens_match(given.as_bytes(), expected.as_bytes()),
+        None => return false,
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        if is_admin(request) {
+            return Outcome::Success(Admin);
+        } else {
+            return Outcome::Error((HttpStatus::Unauthorized, ApiError::NotAdmin));
+        }
+    }
+}
+
+#[test]
+fn test_tokens_match() {
+    assert!(tokens_match(b"correct horse", b"correct horse"));
+    assert!(!tokens_match(b"correct horsf", b"correct horse"));
+    assert!(!tokens_match(b"correct", b"correct horse"));
+    assert!(!tokens_match(b"", b"correct horse"));
+}
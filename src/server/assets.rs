@@ -0,0 +1,94 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::path::Path;
+
+use rocket::http::{ContentType, Header};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+
+// A frontend file compiled into the binary. `name` is its path relative to
+//  `resources/`, which is also how it is requested under `/resources/`.
+pub struct Asset {
+    pub name: &'static str,
+    pub bytes: &'static [u8],
+}
+
+macro_rules! embed {
+    ($name:literal) => {
+        Asset {
+            name: $name,
+            bytes: include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/", $name)),
+        }
+    };
+}
+
+pub const ASSETS: [Asset; 9] = [
+    embed!("common/style.css"),
+    embed!("discussion/buttons.js"),
+    embed!("discussion/discussion.html"),
+    embed!("discussion/display.html"),
+    embed!("discussion/display.js"),
+    embed!("discussion/render.js"),
+    embed!("discussion/socket.js"),
+    embed!("index/buttons.js"),
+    embed!("index/index.html"),
+];
+
+pub fn find(name: &str) -> Option<&'static Asset> {
+    return ASSETS.iter().find(|asset| asset.name == name);
+}
+
+// Pages are always revalidated so that a new binary (or theme) takes effect
+//  straight away. Scripts and stylesheets are allowed to be cached for a while
+//  since every page load would otherwise fetch them again.
+fn cache_control(name: &str) -> &'static str {
+    if name.ends_with(".html") {
+        return "no-cache";
+    } else {
+        return "public, max-age=3600";
+    }
+}
+
+pub struct AssetResponse {
+    name: &'static str,
+    body: Cow<'static, [u8]>,
+}
+
+impl<'r> Responder<'r, 'static> for AssetResponse {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let content_type: ContentType = Path::new(self.name).extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ContentType::from_extension)
+            .unwrap_or(ContentType::Binary);
+        return Response::build()
+            .header(content_type)
+            .header(Header::new("Cache-Control", cache_control(self.name)))
+            .sized_body(self.body.len(), Cursor::new(self.body))
+            .ok();
+    }
+}
+
+// Looks up an embedded asset by name. If an override directory is configured
+//  and holds a file with the same name, that file is served instead, which is
+//  how custom themes replace the bundled frontend. Only names which are
+//  embedded can be overridden.
+pub async fn load(name: &str, override_dir: Option<&Path>) -> Option<AssetResponse> {
+
+    let asset: &'static Asset = find(name)?;
+
+    if let Some(dir) = override_dir {
+        if let Ok(bytes) = rocket::tokio::fs::read(dir.join(asset.name)).await {
+            return Some(AssetResponse { name: asset.name, body: Cow::Owned(bytes) });
+        }
+    }
+
+    return Some(AssetResponse { name: asset.name, body: Cow::Borrowed(asset.bytes) });
+}
+
+#[test]
+fn test_find() {
+    assert!(find("discussion/discussion.html").is_some());
+    assert!(find("common/style.css").is_some());
+    assert!(find("common/missing.css").is_none());
+    assert!(find("../Cargo.toml").is_none());
+}
@@ -0,0 +1,129 @@
+use serde::Deserialize;
+
+use crate::discussion::{AddSpeechResult, Discussion, PriorityMode};
+use crate::discussion::participant::Action;
+use super::messages::ApiError;
+use super::permissions;
+use super::validation::{self, SpeakerName, ValidationError};
+
+// What clients can send over the control socket (see `socket`). Each command
+//  does the same as the HTTP route of the same name, and is checked against the
+//  same permissions, e.g. `{"command": "pause", "paused": true}`.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    Add {
+        #[serde(default)]
+        name: Option<String>,
+        stype: u8,
+    },
+    Next,
+    Previous,
+    Pause {
+        paused: bool,
+    },
+    Alias {
+        name1: String,
+        name2: String,
+    },
+    SetPriorityMode {
+        mode: PriorityMode,
+    },
+}
+
+impl Command {
+
+    // Adding isn't in the permission table, since anybody who has joined can
+    //  add their own speeches. `Discussion::add_speech_as` checks the rest.
+    fn action(&self) -> Option<Action> {
+        match self {
+            Command::Add { .. } => return None,
+            Command::Next => return Some(Action::Next),
+            Command::Previous => return Some(Action::Previous),
+            Command::Pause { .. } => return Some(Action::Pause),
+            Command::Alias { .. } => return Some(Action::Alias),
+            Command::SetPriorityMode { .. } => return Some(Action::SetPriorityMode),
+        }
+    }
+
+    // Unlike the HTTP routes, which quietly ignore most changes to a closed
+    //  discussion, a command always says whether it did anything
+    pub fn run(self, discussion: &mut Discussion, token: Option<&str>) -> Result<(), ApiError> {
+
+        if let Some(action) = self.action() {
+            permissions::check(discussion, token, action)?;
+        }
+        if discussion.is_frozen() {
+            return Err(ApiError::DiscussionIsClosed);
+        }
+
+        match self {
+            Command::Add { name, stype } => {
+                let name: Option<String> = match name {
+                    Some(name) => Some(SpeakerName::parse(&name)?.into_string()),
+                    None => None,
+                };
+                let is_response: bool = validation::parse_speech_type(stype)?;
+                let token: &str = token.ok_or(ApiError::NotJoined)?;
+                return add_speech_result(discussion.add_speech_as(token, name, is_response));
+            },
+            Command::Next => {
+                discussion.goto_next_speech();
+            },
+            Command::Previous => discussion.goto_previous_speech(),
+            Command::Pause { paused } => discussion.set_paused(paused),
+            Command::Alias { name1, name2 } => {
+                let name1: String = SpeakerName::parse(&name1)?.into_string();
+                let name2: String = SpeakerName::parse(&name2)?.into_string();
+                discussion.alias_speakers(&name1, &name2);
+            },
+            Command::SetPriorityMode { mode } => discussion.set_priority_mode(mode),
+        }
+        return Ok(());
+    }
+
+}
+
+pub fn add_speech_result(result: AddSpeechResult) -> Result<(), ApiError> {
+    match result {
+        AddSpeechResult::Success => return Ok(()),
+        AddSpeechResult::EmptyName => return Err(ValidationError::EmptySpeakerName.into()),
+        AddSpeechResult::TooManyQueuedSpeeches => return Err(ApiError::TooManyQueuedSpeeches),
+        AddSpeechResult::NotJoined => return Err(ApiError::NotJoined),
+        AddSpeechResult::OnlyChairCanAddForOthers => return Err(ApiError::OnlyChairCanAddForOthers),
+        // Responses with nothing to respond to, and speeches added after the
+        //  discussion closed, have always been quietly dropped
+        AddSpeechResult::NothingToRespondTo | AddSpeechResult::DiscussionIsFrozen => return Ok(()),
+    }
+}
+
+#[test]
+fn test_commands_parse() {
+    let parse = |json: &str| serde_json::from_str::<Command>(json).ok();
+    assert_eq!(parse(r#"{"command": "next"}"#), Some(Command::Next));
+    assert_eq!(parse(r#"{"id": 4, "command": "pause", "paused": true}"#), Some(Command::Pause { paused: true }));
+    assert_eq!(parse(r#"{"command": "add", "stype": 1}"#), Some(Command::Add { name: None, stype: 1 }));
+    assert_eq!(parse(r#"{"command": "set_priority_mode", "mode": "brevity"}"#), Some(Command::SetPriorityMode { mode: PriorityMode::FavourBriefest }));
+    assert_eq!(parse(r#"{"command": "pause"}"#), None);
+    assert_eq!(parse(r#"{"command": "close"}"#), None);
+}
+
+#[test]
+fn test_commands_check_the_senders_role() {
+    use crate::discussion::DiscussionSettings;
+    let discussion = Discussion::new(DiscussionSettings::default());
+    let mut discussion = discussion.lock().unwrap();
+    discussion.add_chair("chair".to_string());
+    discussion.join("alice".to_string(), "Alice".to_string());
+
+    assert!(matches!(Command::Pause { paused: true }.run(&mut discussion, Some("alice")), Err(ApiError::NotPermitted)));
+    assert!(matches!(Command::Next.run(&mut discussion, None), Err(ApiError::NotJoined)));
+    assert!(!discussion.paused);
+    assert!(Command::Add { name: None, stype: 1 }.run(&mut discussion, Some("alice")).is_ok());
+    assert!(matches!(Command::Add { name: None, stype: 7 }.run(&mut discussion, Some("alice")), Err(ApiError::Invalid(_))));
+    assert!(Command::Pause { paused: true }.run(&mut discussion, Some("chair")).is_ok());
+    assert!(discussion.paused);
+
+    discussion.close();
+    assert!(matches!(Command::Next.run(&mut discussion, Some("chair")), Err(ApiError::DiscussionIsClosed)));
+}
@@ -0,0 +1,269 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+
+use crate::discussion::{DiscussionSettings, PriorityMode};
+use crate::discussion::roster::Roster;
+
+// Server settings. These are read through Rocket's figment, so they can be set
+//  in the `[default]` (or any other profile) table of `Rocket.toml`, or with
+//  `ROCKET_`-prefixed environment variables (e.g. `ROCKET_ROOM_TTL=3600`).
+//  Every duration is given in whole seconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub resource_dir: Option<PathBuf>,
+    pub data_dir: PathBuf,
+    pub roster_dir: Option<PathBuf>,
+    pub default_priority_mode: PriorityMode,
+    pub new_point_time_limit: Option<u64>,
+    pub response_time_limit: Option<u64>,
+    pub poll_interval: u64,
+    pub room_ttl: u64,
+    pub max_discussions: usize,
+    pub max_queued_speeches_per_speaker: usize,
+    pub client_rate_limit: u32,
+    pub discussion_rate_limit: u32,
+    pub discussion_creation_limit: u32,
+    pub webhooks: Vec<String>,
+    pub webhook_secret: Option<String>,
+    pub webhook_attempts: u32,
+    pub admin_token: Option<String>,
+    pub history_db: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            resource_dir: None,
+            data_dir: PathBuf::from("data"),
+            roster_dir: None,
+            default_priority_mode: PriorityMode::FirstComeFirstServe,
+            new_point_time_limit: None,
+            response_time_limit: None,
+            poll_interval: 1,
+            room_ttl: 12 * 60 * 60,
+            max_discussions: 1000,
+            max_queued_speeches_per_speaker: 3,
+            client_rate_limit: 60,
+            discussion_rate_limit: 600,
+            discussion_creation_limit: 10,
+            webhooks: Vec::new(),
+            webhook_secret: None,
+            webhook_attempts: 5,
+            admin_token: None,
+            history_db: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    ResourceDirMissing(PathBuf),
+    RosterDirMissing(PathBuf),
+    DataDirUnusable(PathBuf, String),
+    ZeroTimeLimit(&'static str),
+    PollIntervalOutOfRange(u64),
+    RoomTtlTooShort(u64),
+    NoDiscussionsAllowed,
+    ZeroLimit(&'static str),
+    BadWebhookUrl(String),
+    WebhookSecretMissing,
+    AdminTokenTooShort,
+    HistoryDbUnsupported,
+    HistoryDbUnusable(PathBuf, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ResourceDirMissing(path) => write!(f, "resource directory {} does not exist (check `resource_dir`)", path.display()),
+            ConfigError::RosterDirMissing(path) => write!(f, "roster directory {} does not exist (check `roster_dir`)", path.display()),
+            ConfigError::DataDirUnusable(path, e) => write!(f, "data directory {} cannot be created: {} (check `data_dir`)", path.display(), e),
+            ConfigError::ZeroTimeLimit(key) => write!(f, "`{}` must be at least one second; leave it unset for no limit", key),
+            ConfigError::PollIntervalOutOfRange(secs) => write!(f, "`poll_interval` must be between 1 and {} seconds, not {}", MAX_POLL_INTERVAL, secs),
+            ConfigError::RoomTtlTooShort(secs) => write!(f, "`room_ttl` must be at least {} seconds, not {}", MIN_ROOM_TTL, secs),
+            ConfigError::NoDiscussionsAllowed => write!(f, "`max_discussions` must be at least 1"),
+            ConfigError::ZeroLimit(key) => write!(f, "`{}` must be at least 1", key),
+            ConfigError::BadWebhookUrl(url) => write!(f, "webhook {:?} must be an http:// or https:// URL (check `webhooks`)", url),
+            ConfigError::WebhookSecretMissing => write!(f, "`webhook_secret` must be set to sign what is sent to `webhooks`"),
+            ConfigError::AdminTokenTooShort => write!(f, "`admin_token` must be at least {} characters long; leave it unset to turn the admin API off", MIN_ADMIN_TOKEN_LENGTH),
+            ConfigError::HistoryDbUnsupported => write!(f, "`history_db` needs Speakeasy to be built with the `sqlite` feature"),
+            ConfigError::HistoryDbUnusable(path, e) => write!(f, "history database {} cannot be opened: {} (check `history_db`)", path.display(), e),
+        }
+    }
+}
+
+// Past this point the speaking order shown to participants is too stale to be
+//  useful
+const MAX_POLL_INTERVAL: u64 = 60;
+
+// Anything shorter than this risks evicting a discussion during a quiet
+//  stretch of an ongoing meeting
+const MIN_ROOM_TTL: u64 = 60;
+
+// The admin token opens every discussion, so it shouldn't be guessable
+const MIN_ADMIN_TOKEN_LENGTH: usize = 16;
+
+impl Config {
+
+    // Checks every setting and returns all of the problems at once so that they
+    //  can be fixed in one go. Note that this creates `data_dir` (and
+    //  `history_db`) if needed.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+
+        let mut errors: Vec<ConfigError> = Vec::new();
+
+        if let Some(resource_dir) = &self.resource_dir {
+            if !resource_dir.is_dir() {
+                errors.push(ConfigError::ResourceDirMissing(resource_dir.clone()));
+            }
+        }
+
+        if let Some(roster_dir) = &self.roster_dir {
+            if !roster_dir.is_dir() {
+                errors.push(ConfigError::RosterDirMissing(roster_dir.clone()));
+            }
+        }
+
+        if let Err(e) = fs::create_dir_all(&self.data_dir) {
+            errors.push(ConfigError::DataDirUnusable(self.data_dir.clone(), e.to_string()));
+        }
+
+        if self.new_point_time_limit == Some(0) {
+            errors.push(ConfigError::ZeroTimeLimit("new_point_time_limit"));
+        }
+
+        if self.response_time_limit == Some(0) {
+            errors.push(ConfigError::ZeroTimeLimit("response_time_limit"));
+        }
+
+        if self.poll_interval == 0 || self.poll_interval > MAX_POLL_INTERVAL {
+            errors.push(ConfigError::PollIntervalOutOfRange(self.poll_interval));
+        }
+
+        if self.room_ttl < MIN_ROOM_TTL {
+            errors.push(ConfigError::RoomTtlTooShort(self.room_ttl));
+        }
+
+        if self.max_discussions == 0 {
+            errors.push(ConfigError::NoDiscussionsAllowed);
+        }
+
+        for (key, limit) in [
+            ("max_queued_speeches_per_speaker", self.max_queued_speeches_per_speaker),
+            ("client_rate_limit", self.client_rate_limit as usize),
+            ("discussion_rate_limit", self.discussion_rate_limit as usize),
+            ("discussion_creation_limit", self.discussion_creation_limit as usize),
+            ("webhook_attempts", self.webhook_attempts as usize),
+        ] {
+            if limit == 0 {
+                errors.push(ConfigError::ZeroLimit(key));
+            }
+        }
+
+        for url in &self.webhooks {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                errors.push(ConfigError::BadWebhookUrl(url.clone()));
+            }
+        }
+
+        if !self.webhooks.is_empty() && self.webhook_secret.as_deref().is_none_or(str::is_empty) {
+            errors.push(ConfigError::WebhookSecretMissing);
+        }
+
+        if self.admin_token.as_ref().is_some_and(|token| token.len() < MIN_ADMIN_TOKEN_LENGTH) {
+            errors.push(ConfigError::AdminTokenTooShort);
+        }
+
+        if let Some(history_db) = &self.history_db {
+            #[cfg(feature = "sqlite")]
+            if let Err(e) = crate::storage::sqlite::SqliteStore::open(history_db) {
+                errors.push(ConfigError::HistoryDbUnusable(history_db.clone(), e.0));
+            }
+            #[cfg(not(feature = "sqlite"))]
+            {
+                let _ = history_db;
+                errors.push(ConfigError::HistoryDbUnsupported);
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        } else {
+            return Err(errors);
+        }
+    }
+
+    pub fn discussion_settings(&self) -> DiscussionSettings {
+        DiscussionSettings {
+            priority_mode: self.default_priority_mode,
+            new_point_time_limit: self.new_point_time_limit.map(Duration::from_secs),
+            response_time_limit: self.response_time_limit.map(Duration::from_secs),
+            max_queued_speeches_per_speaker: Some(self.max_queued_speeches_per_speaker),
+            roster: Roster::default(),
+            series: None,
+            rank_by_series: false,
+        }
+    }
+
+    pub fn room_ttl(&self) -> Duration {
+        return Duration::from_secs(self.room_ttl);
+    }
+
+}
+
+#[test]
+fn test_default_config_is_valid() {
+    let config: Config = Config {
+        data_dir: std::env::temp_dir().join("speakeasy-test-data"),
+        ..Config::default()
+    };
+    assert_eq!(config.validate(), Ok(()));
+}
+
+#[test]
+fn test_validate_reports_every_error() {
+    let config: Config = Config {
+        resource_dir: Some(PathBuf::from("no/such/dir")),
+        data_dir: std::env::temp_dir().join("speakeasy-test-data"),
+        new_point_time_limit: Some(0),
+        poll_interval: 0,
+        room_ttl: 5,
+        max_discussions: 0,
+        client_rate_limit: 0,
+        webhooks: vec!["ftp://example.com/hook".to_string()],
+        admin_token: Some("hunter2".to_string()),
+        ..Config::default()
+    };
+    assert_eq!(config.validate(), Err(vec![
+        ConfigError::ResourceDirMissing(PathBuf::from("no/such/dir")),
+        ConfigError::ZeroTimeLimit("new_point_time_limit"),
+        ConfigError::PollIntervalOutOfRange(0),
+        ConfigError::RoomTtlTooShort(5),
+        ConfigError::NoDiscussionsAllowed,
+        ConfigError::ZeroLimit("client_rate_limit"),
+        ConfigError::BadWebhookUrl("ftp://example.com/hook".to_string()),
+        ConfigError::WebhookSecretMissing,
+        ConfigError::AdminTokenTooShort,
+    ]));
+}
+
+#[test]
+fn test_history_db_is_checked() {
+    let config: Config = Config {
+        data_dir: std::env::temp_dir().join("speakeasy-test-data"),
+        history_db: Some(PathBuf::from("no/such/dir/history.sqlite")),
+        ..Config::default()
+    };
+    match config.validate() {
+        #[cfg(feature = "sqlite")]
+        Err(errors) => assert!(matches!(errors[..], [ConfigError::HistoryDbUnusable(_, _)])),
+        #[cfg(not(feature = "sqlite"))]
+        Err(errors) => assert_eq!(errors, vec![ConfigError::HistoryDbUnsupported]),
+        Ok(()) => panic!("an unusable `history_db` was accepted"),
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use debug_panic::debug_panic;
+use lazy_static::lazy_static;
+
+use super::config::Config;
+use crate::discussion::Discussion;
+use crate::storage::history::{DiscussionState, Recorder, Store, StoreError};
+
+// Everything for the writer goes down one queue, so a discussion being
+//  forgotten can't overtake the last of its changes
+enum Message {
+    Record(String, DiscussionState),
+    Forget(String),
+}
+
+lazy_static! {
+    // The store is only ever touched by the writer's own thread, so a slow disk
+    //  holds up the history rather than the discussions. Nothing is recorded
+    //  until `start` is called, and never if `history_db` isn't set.
+    static ref QUEUE: Mutex<Option<Sender<Message>>> = Mutex::new(None);
+}
+
+pub fn start(config: &Config) {
+
+    let history_db = match &config.history_db {
+        Some(history_db) => history_db,
+        None => return,
+    };
+
+    match open_store(history_db) {
+        Ok(store) => match QUEUE.lock() {
+            Ok(mut queue) => *queue = Some(spawn_writer(Recorder::new(store)).0),
+            Err(e) => debug_panic!(e.to_string()),
+        },
+        Err(e) => error!("Not recording the history of discussions in {}: {}", history_db.display(), e),
+    }
+
+}
+
+#[cfg(feature = "sqlite")]
+fn open_store(history_db: &Path) -> Result<Box<dyn Store>, StoreError> {
+    return Ok(Box::new(crate::storage::sqlite::SqliteStore::open(history_db)?));
+}
+
+// `Config::validate` doesn't let this happen
+#[cfg(not(feature = "sqlite"))]
+fn open_store(_history_db: &Path) -> Result<Box<dyn Store>, StoreError> {
+    debug_panic!("`history_db` needs the `sqlite` feature");
+    return Err(StoreError("Speakeasy was built without the `sqlite` feature".to_string()));
+}
+
+// The writer hands the recorder back once every sender has gone
+fn spawn_writer(mut recorder: Recorder) -> (Sender<Message>, thread::JoinHandle<Recorder>) {
+    let (sender, receiver) = mpsc::channel::<Message>();
+    let writer = thread::spawn(move || {
+        for message in receiver {
+            match message {
+                Message::Record(id, state) => if let Err(e) = recorder.record(&id, state) {
+                    error!("Could not record discussion {:?} in the history: {}", id, e);
+                },
+                Message::Forget(id) => recorder.forget(&id),
+            }
+        }
+        return recorder;
+    });
+    return (sender, writer);
+}
+
+// The sender is cloned out so that the queue isn't held while a discussion is
+//  copied
+fn queue() -> Option<Sender<Message>> {
+    match QUEUE.lock() {
+        Ok(queue) => return queue.clone(),
+        Err(e) => {
+            debug_panic!(e.to_string());
+            return None;
+        }
+    }
+}
+
+// Queues whatever has changed in the discussion since it was last recorded.
+//  Called with the discussion locked, so that changes are queued in order, but
+//  only the copy is made here; the writer works out and writes the rows.
+pub fn record(id: &str, discussion: &Discussion) {
+    if let Some(queue) = queue() {
+        let _ = queue.send(Message::Record(id.to_string(), DiscussionState::take(discussion)));
+    }
+}
+
+// Once a discussion has been archived its ID is free for a new one, which
+//  gets a history of its own
+pub fn forget(id: &str) {
+    if let Some(queue) = queue() {
+        let _ = queue.send(Message::Forget(id.to_string()));
+    }
+}
+
+#[test]
+fn test_the_writer_keeps_the_order_it_was_given() {
+
+    use std::sync::Arc;
+    use chrono::prelude::*;
+    use crate::discussion::DiscussionSettings;
+    use crate::storage::history::SpeakerTotal;
+    use crate::storage::memory::MemoryStore;
+
+    let (queue, writer) = spawn_writer(Recorder::new(Box::new(MemoryStore::default())));
+
+    let board: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut board = board.lock().unwrap();
+    board.add_new_speech("Ada".to_string(), false);
+    board.tick_clock();
+    queue.send(Message::Record("board".to_string(), DiscussionState::take(&board))).unwrap();
+    queue.send(Message::Forget("board".to_string())).unwrap();
+    board.tick_clock();
+    queue.send(Message::Record("board".to_string(), DiscussionState::take(&board))).unwrap();
+    drop(queue);
+
+    // Forgetting in between makes the second copy a discussion of its own
+    let recorder: Recorder = writer.join().unwrap();
+    let totals: Vec<SpeakerTotal> = recorder.store().speaker_totals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap();
+    assert_eq!(totals, vec![SpeakerTotal { name: "Ada".to_string(), discussions: 2, speeches: 2, speaking_time: 3 }]);
+}
@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+use rocket::http::Status as HttpStatus;
+use rocket::request::Request;
+use rocket::response::{self, status, Responder};
+
+use super::validation::ValidationError;
+use crate::discussion::roster::RosterError;
+use crate::discussion::portable::PortableError;
+use crate::discussion::speech::SpeechId;
+use crate::discussion::Lifecycle;
+use crate::discussion::participant::Role;
+
+// Participants leave out `name` (it is always their own); the chair can give
+//  any name to add a speech on someone else's behalf
+#[derive(Deserialize)]
+pub struct NewSpeakerRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub stype: u8,
+}
+
+#[derive(Deserialize)]
+pub struct JoinRequest {
+    pub name: String,
+}
+
+// Leaving out `speeches` splits off the speeches which were added under the
+//  name being split
+#[derive(Deserialize)]
+pub struct SplitSpeakerRequest {
+    #[serde(default)]
+    pub speeches: Option<Vec<SpeechId>>,
+}
+
+// `moved_by` is whoever moved the motion, who needn't have joined
+#[derive(Deserialize)]
+pub struct MotionRequest {
+    pub text: String,
+    pub moved_by: String,
+}
+
+// `display_token` is only sent to roles which can share the projector view
+#[derive(Serialize)]
+pub struct SessionReport {
+    pub name: Option<String>,
+    pub is_chair: bool,
+    pub role: Option<Role>,
+    pub display_token: Option<String>,
+}
+
+#[derive(Serialize)]
+pub enum Status {
+    Normal,
+    Paused,
+    Closed,
+    NonExistant,
+    ServerError,
+}
+
+// One speech in the speaking order, for clients which lay it out themselves
+//  rather than showing `StatusReport::speaking_order`. Times are in seconds.
+#[derive(Serialize)]
+pub struct SpeechReport {
+    pub id: SpeechId,
+    pub speaker: String,
+    pub stype: u8,
+    pub duration: u64,
+    pub time_limit: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct StatusReport {
+    pub status: Status,
+    pub speaking_order: String,
+    pub current: Option<SpeechReport>,
+    pub upcoming: Vec<SpeechReport>,
+    pub duration: String,
+    pub poll_interval: u64,
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+impl StatusReport {
+    pub fn default(status: Status, poll_interval: u64) -> Self {
+        StatusReport {
+            status: status,
+            speaking_order: "".to_string(),
+            current: None,
+            upcoming: Vec::new(),
+            duration: "".to_string(),
+            poll_interval: poll_interval,
+            can_undo: false,
+            can_redo: false,
+        }
+    }
+}
+
+// One line of the admin API's list of discussions. Times are in seconds.
+#[derive(Serialize)]
+pub struct DiscussionSummary {
+    pub id: String,
+    pub lifecycle: Lifecycle,
+    pub paused: bool,
+    pub participants: usize,
+    pub queued_speeches: usize,
+    pub duration: u64,
+    pub idle_for: u64,
+}
+
+// What the server sends over the control socket. Each command gets exactly one
+//  `Result` back, carrying the `id` the client sent with it (or none, if the
+//  command couldn't be read at all). `State` carries the fields of the status
+//  report which changed since the last one sent; the first has all of them.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SocketMessage {
+    Result {
+        id: Option<u64>,
+        error: Option<SocketError>,
+    },
+    State {
+        changes: serde_json::Map<String, serde_json::Value>,
+    },
+}
+
+#[derive(Serialize)]
+pub struct SocketError {
+    pub kind: String,
+    pub status: u16,
+    pub message: String,
+}
+
+impl From<ApiError> for SocketError {
+    fn from(e: ApiError) -> Self {
+        let (status, message): (HttpStatus, String) = e.status_and_message();
+        SocketError {
+            kind: e.kind(),
+            status: status.code,
+            message: message,
+        }
+    }
+}
+
+// Every way a request can be refused. Each one is sent back with a fitting
+//  status code and a short plain-text explanation.
+#[derive(Debug)]
+pub enum ApiError {
+    Invalid(ValidationError),
+    InvalidRoster(RosterError),
+    InvalidState(PortableError),
+    StateTooLarge,
+    DiscussionExists,
+    NoDiscussionFoundWithGivenID,
+    NoSeriesFoundWithGivenName,
+    WrongSeriesSecret,
+    TooManyDiscussions,
+    TooManyQueuedSpeeches,
+    RateLimited(Duration),
+    NotJoined,
+    NotPermitted,
+    NotAdmin,
+    OnlyChairCanAddForOthers,
+    AlreadyJoined,
+    NameTaken,
+    NoSpeakerFoundWithGivenName,
+    NoParticipantWithGivenName,
+    LastChair,
+    NotAnAlias,
+    NoSuchSpeech(SpeechId),
+    SpeechNotBySpeaker(SpeechId),
+    SpeechAlreadyGiven,
+    OnlyChairCanWithdrawForOthers,
+    NothingToUndo,
+    NothingToRedo,
+    NoSuchMotion(usize),
+    MotionAlreadyResolved(usize),
+    DiscussionIsClosed,
+    ServerError,
+}
+
+impl From<ValidationError> for ApiError {
+    fn from(e: ValidationError) -> Self {
+        ApiError::Invalid(e)
+    }
+}
+
+impl From<RosterError> for ApiError {
+    fn from(e: RosterError) -> Self {
+        ApiError::InvalidRoster(e)
+    }
+}
+
+impl From<PortableError> for ApiError {
+    fn from(e: PortableError) -> Self {
+        ApiError::InvalidState(e)
+    }
+}
+
+// Retry-After only takes whole seconds, and rounding down would have the client
+//  come back too early
+fn retry_after(wait: &Duration) -> u64 {
+    return wait.as_secs() + if wait.subsec_nanos() > 0 { 1 } else { 0 };
+}
+
+impl ApiError {
+
+    // The variant's name, which is what clients match on when errors come
+    //  back over the control socket rather than as an HTTP status
+    pub fn kind(&self) -> String {
+        let name: String = format!("{:?}", self);
+        return name.split('(').next().unwrap_or_default().to_string();
+    }
+
+    pub fn status_and_message(&self) -> (HttpStatus, String) {
+        return match self {
+            ApiError::Invalid(e) => (HttpStatus::BadRequest, e.to_string()),
+            ApiError::InvalidRoster(e) => (HttpStatus::BadRequest, e.to_string()),
+            ApiError::InvalidState(e) => (HttpStatus::BadRequest, e.to_string()),
+            ApiError::StateTooLarge => (HttpStatus::PayloadTooLarge, "the discussion is larger than the server accepts".to_string()),
+            ApiError::DiscussionExists => (HttpStatus::Conflict, "a discussion with that ID already exists".to_string()),
+            ApiError::NoDiscussionFoundWithGivenID => (HttpStatus::NotFound, "no discussion exists with the given ID".to_string()),
+            ApiError::NoSeriesFoundWithGivenName => (HttpStatus::NotFound, "no series exists with the given name".to_string()),
+            ApiError::WrongSeriesSecret => (HttpStatus::Forbidden, "joining this series needs its secret".to_string()),
+            ApiError::TooManyDiscussions => (HttpStatus::TooManyRequests, "the server cannot hold any more discussions right now".to_string()),
+            ApiError::TooManyQueuedSpeeches => (HttpStatus::TooManyRequests, "this speaker already has as many speeches waiting as they are allowed".to_string()),
+            ApiError::RateLimited(wait) => (HttpStatus::TooManyRequests, format!("too many requests; try again in {} seconds", retry_after(wait))),
+            ApiError::NotJoined => (HttpStatus::Forbidden, "you need to join the discussion first".to_string()),
+            ApiError::NotPermitted => (HttpStatus::Forbidden, "your role in this discussion doesn't allow that".to_string()),
+            ApiError::NotAdmin => (HttpStatus::Unauthorized, "this needs the server's admin token".to_string()),
+            ApiError::OnlyChairCanAddForOthers => (HttpStatus::Forbidden, "only the chair can add speeches for other people".to_string()),
+            ApiError::AlreadyJoined => (HttpStatus::Conflict, "you have already joined this discussion".to_string()),
+            ApiError::NameTaken => (HttpStatus::Conflict, "somebody has already joined under that name".to_string()),
+            ApiError::NoSpeakerFoundWithGivenName => (HttpStatus::NotFound, "nobody has spoken or been aliased under that name".to_string()),
+            ApiError::NoParticipantWithGivenName => (HttpStatus::NotFound, "nobody has joined under that name".to_string()),
+            ApiError::LastChair => (HttpStatus::Conflict, "the last chair can't give up the role".to_string()),
+            ApiError::NotAnAlias => (HttpStatus::Conflict, "that name is not an alias of somebody else, so there is nothing to split".to_string()),
+            ApiError::NoSuchSpeech(id) => (HttpStatus::NotFound, format!("there is no speech #{}", id.0)),
+            ApiError::SpeechNotBySpeaker(id) => (HttpStatus::Conflict, format!("speech #{} was not given by the speaker being split", id.0)),
+            ApiError::SpeechAlreadyGiven => (HttpStatus::Conflict, "that speech has already been given".to_string()),
+            ApiError::OnlyChairCanWithdrawForOthers => (HttpStatus::Forbidden, "only the chair can withdraw other people's speeches".to_string()),
+            ApiError::NothingToUndo => (HttpStatus::Conflict, "there is nothing left to undo".to_string()),
+            ApiError::NothingToRedo => (HttpStatus::Conflict, "there is nothing left to redo".to_string()),
+            ApiError::NoSuchMotion(number) => (HttpStatus::NotFound, format!("there is no motion #{}", number)),
+            ApiError::MotionAlreadyResolved(number) => (HttpStatus::Conflict, format!("motion #{} has already been dealt with", number)),
+            ApiError::DiscussionIsClosed => (HttpStatus::Conflict, "this discussion has been closed".to_string()),
+            ApiError::ServerError => (HttpStatus::InternalServerError, "something went wrong on the server".to_string()),
+        };
+    }
+
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let (status, message): (HttpStatus, String) = self.status_and_message();
+        let mut response = status::Custom(status, message).respond_to(request)?;
+        if let ApiError::RateLimited(wait) = &self {
+            response.set_raw_header("Retry-After", retry_after(wait).to_string());
+        }
+        if let ApiError::NotAdmin = &self {
+            response.set_raw_header("WWW-Authenticate", "Bearer");
+        }
+        return Ok(response);
+    }
+}
@@ -0,0 +1,95 @@
+use std::marker::PhantomData;
+
+use rocket::request::{self, FromRequest, Request};
+use rocket::outcome::Outcome;
+use rocket::http::Status as HttpStatus;
+
+use super::{admin, get_discussion, GetDiscussionError};
+use crate::discussion::Discussion;
+use crate::discussion::participant::Action;
+use super::messages::ApiError;
+use super::session::SESSION_COOKIE;
+
+// Ties a type to one of the actions in the permission table, so that a route
+//  can say what it needs in its signature, e.g. `Result<Allowed<can::Next>, ApiError>`
+pub trait Guarded {
+    const ACTION: Action;
+}
+
+macro_rules! guarded_actions {
+    ($($action:ident),* $(,)?) => {
+        pub mod can {
+            use super::{Action, Guarded};
+            $(
+                pub struct $action;
+                impl Guarded for $action {
+                    const ACTION: Action = Action::$action;
+                }
+            )*
+        }
+    };
+}
+
+guarded_actions!(Next, Previous, Pause, SetPriorityMode, Alias, Split, Undo, Motions, UploadRoster, AssignRoles, Export, Close, Archive);
+
+// A request guard for routes under `/discussion/<id>/` which only some roles
+//  can use. It looks up the role of the session holder in that discussion and
+//  refuses the request unless the role is allowed to do `A`. Requests carrying
+//  the admin token are always allowed.
+pub struct Allowed<A: Guarded>(PhantomData<A>);
+
+#[rocket::async_trait]
+impl<'r, A: Guarded> FromRequest<'r> for Allowed<A> {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+
+        let id: &str = match (request.routed_segment(0), request.routed_segment(1)) {
+            (Some("discussion"), Some(id)) => id,
+            _ => return Outcome::Error((HttpStatus::NotFound, ApiError::NoDiscussionFoundWithGivenID)),
+        };
+
+        let token: Option<&str> = request.cookies().get(SESSION_COOKIE).map(|cookie| cookie.value());
+        let allowed: Result<(), ApiError> = match get_discussion(id) {
+            Ok(discussion) => match discussion.lock() {
+                Ok(_) if admin::is_admin(request) => Ok(()),
+                Ok(_) if request.headers().contains("Authorization") => Err(ApiError::NotAdmin),
+                Ok(locked_discussion) => check(&locked_discussion, token, A::ACTION),
+                Err(_) => Err(ApiError::ServerError),
+            },
+            Err(GetDiscussionError::NoDiscussionFoundWithGivenID) => Err(ApiError::NoDiscussionFoundWithGivenID),
+            Err(GetDiscussionError::CouldNotLock) => Err(ApiError::ServerError),
+        };
+
+        match allowed {
+            Ok(()) => return Outcome::Success(Allowed(PhantomData)),
+            Err(e) => return Outcome::Error((e.status_and_message().0, e)),
+        }
+    }
+}
+
+// The check behind `Allowed`, for callers which aren't handling a request of
+//  their own (e.g. commands sent over the control socket)
+pub fn check(discussion: &Discussion, token: Option<&str>, action: Action) -> Result<(), ApiError> {
+    match token.and_then(|token| discussion.role_of(token)) {
+        Some(role) if role.can(action) => return Ok(()),
+        Some(_) => return Err(ApiError::NotPermitted),
+        None => return Err(ApiError::NotJoined),
+    }
+}
+
+// Sent by chairs joining a series with the secret they were given when the
+//  series was started
+pub const SERIES_SECRET_HEADER: &str = "X-Speakeasy-Series-Secret";
+
+// A request guard which never fails; routes decide what a missing secret means
+pub struct SeriesSecret(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SeriesSecret {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        return Outcome::Success(SeriesSecret(request.headers().get_one(SERIES_SECRET_HEADER).map(|secret| secret.to_string())));
+    }
+}
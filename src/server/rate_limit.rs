@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rocket::request::{self, FromRequest, Request};
+use rocket::outcome::Outcome;
+use rocket::http::Status as HttpStatus;
+use debug_panic::debug_panic;
+
+use super::{get_discussion, config::Config};
+use super::messages::ApiError;
+use super::session::SESSION_COOKIE;
+
+// Once a limiter is tracking this many keys, the ones which have completely
+//  refilled (and so carry no information) are dropped
+const PRUNE_THRESHOLD: usize = 1024;
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+// A token bucket per key. Each key can make `limit` requests in a burst, after
+//  which it regains the ability to make one more every `period / limit`.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+
+    pub fn new(limit: u32, period: Duration) -> Self {
+        RateLimiter {
+            capacity: limit as f64,
+            refill_per_second: limit as f64 / period.as_secs_f64(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Uses up one request for `key`. If there are none left, returns how long
+    //  the caller needs to wait before trying again
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        return self.check_at(key, Instant::now());
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                debug_panic!(e.to_string());
+                return Ok(());
+            }
+        };
+
+        if buckets.len() >= PRUNE_THRESHOLD {
+            let (capacity, refill_per_second) = (self.capacity, self.refill_per_second);
+            buckets.retain(|_, bucket| bucket.tokens + refill_per_second * now.saturating_duration_since(bucket.updated_at).as_secs_f64() < capacity);
+        }
+
+        let bucket: &mut Bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+
+        let elapsed: f64 = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = f64::min(self.capacity, bucket.tokens + elapsed * self.refill_per_second);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        } else {
+            return Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_second));
+        }
+
+    }
+
+}
+
+pub struct RateLimits {
+    pub per_client: RateLimiter,
+    pub per_discussion: RateLimiter,
+    pub discussion_creation: RateLimiter,
+}
+
+impl RateLimits {
+    pub fn new(config: &Config) -> Self {
+        RateLimits {
+            per_client: RateLimiter::new(config.client_rate_limit, Duration::from_secs(60)),
+            per_discussion: RateLimiter::new(config.discussion_rate_limit, Duration::from_secs(60)),
+            discussion_creation: RateLimiter::new(config.discussion_creation_limit, Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+// A request guard for every route which changes something. It charges the
+//  request to the client and, for routes under `/discussion/<id>/`, to that
+//  discussion as well. Participants are told apart by their session, so that
+//  everyone at a venue behind one address (e.g. a NAT) doesn't share a bucket.
+//  Anyone else is charged to their IP address, which is also what `client`
+//  holds, so behind a reverse proxy Rocket's `ip_header` setting needs to be
+//  correct.
+pub struct WithinRateLimit {
+    pub client: String,
+}
+
+// Anyone can send whatever cookie they like, so a session only counts once the
+//  discussion knows it. Otherwise making up a new one for every request would
+//  get around the limit.
+fn client_key(request: &Request<'_>, ip: &str) -> String {
+    let (id, token) = match (request.routed_segment(0), request.routed_segment(1), request.cookies().get(SESSION_COOKIE)) {
+        (Some("discussion"), Some(id), Some(cookie)) => (id, cookie.value()),
+        _ => return ip.to_string(),
+    };
+    let is_session: bool = match get_discussion(id) {
+        Ok(discussion) => match discussion.lock() {
+            Ok(locked_discussion) => locked_discussion.role_of(token).is_some(),
+            Err(_) => false,
+        },
+        Err(_) => false,
+    };
+    if is_session {
+        return format!("session {}", token);
+    } else {
+        return ip.to_string();
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WithinRateLimit {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+
+        let limits: &RateLimits = match request.rocket().state::<RateLimits>() {
+            Some(limits) => limits,
+            None => {
+                debug_panic!();
+                return Outcome::Error((HttpStatus::InternalServerError, ApiError::ServerError));
+            }
+        };
+
+        let client: String = request.client_ip().map(|ip| ip.to_string()).unwrap_or_default();
+
+        if let Err(wait) = limits.per_client.check(&client_key(request, &client)) {
+            return Outcome::Error((HttpStatus::TooManyRequests, ApiError::RateLimited(wait)));
+        }
+
+        if let (Some("discussion"), Some(id)) = (request.routed_segment(0), request.routed_segment(1)) {
+            if let Err(wait) = limits.per_discussion.check(id) {
+                return Outcome::Error((HttpStatus::TooManyRequests, ApiError::RateLimited(wait)));
+            }
+        }
+
+        return Outcome::Success(WithinRateLimit { client: client });
+    }
+}
+
+#[test]
+fn test_burst_then_refill() {
+    let limiter: RateLimiter = RateLimiter::new(3, Duration::from_secs(60));
+    let start: Instant = Instant::now();
+    for _ in 0..3 {
+        assert_eq!(limiter.check_at("a", start), Ok(()));
+    }
+    assert_eq!(limiter.check_at("a", start), Err(Duration::from_secs(20)));
+
+    // Other keys have their own buckets
+    assert_eq!(limiter.check_at("b", start), Ok(()));
+
+    assert!(limiter.check_at("a", start + Duration::from_secs(19)).is_err());
+    assert_eq!(limiter.check_at("a", start + Duration::from_secs(20)), Ok(()));
+    assert!(limiter.check_at("a", start + Duration::from_secs(20)).is_err());
+
+    // Waiting longer than it takes to refill completely never gives more than
+    //  a full burst
+    let later: Instant = start + Duration::from_secs(60 * 60);
+    for _ in 0..3 {
+        assert_eq!(limiter.check_at("a", later), Ok(()));
+    }
+    assert!(limiter.check_at("a", later).is_err());
+}
+
+#[test]
+fn test_full_buckets_are_pruned() {
+    let limiter: RateLimiter = RateLimiter::new(2, Duration::from_secs(60));
+    let start: Instant = Instant::now();
+    for i in 0..PRUNE_THRESHOLD {
+        assert_eq!(limiter.check_at(&i.to_string(), start), Ok(()));
+    }
+    assert_eq!(limiter.check_at("busy", start), Ok(()));
+    assert_eq!(limiter.check_at("busy", start), Ok(()));
+    assert_eq!(limiter.check_at("late", start + Duration::from_secs(60)), Ok(()));
+    let buckets = limiter.buckets.lock().unwrap();
+    assert!(buckets.len() < PRUNE_THRESHOLD);
+    assert!(buckets.contains_key("late"));
+}
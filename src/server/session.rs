@@ -0,0 +1,36 @@
+use rand::Rng;
+use rocket::http::{Cookie, SameSite};
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+
+// Each discussion gets its own cookie, scoped to the discussion's path, which
+//  holds the token identifying the participant in that discussion.
+pub const SESSION_COOKIE: &str = "speakeasy_session";
+
+pub struct Session {
+    pub token: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Session {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match request.cookies().get(SESSION_COOKIE) {
+            Some(cookie) => return Outcome::Success(Session { token: cookie.value().to_string() }),
+            None => return Outcome::Forward(rocket::http::Status::Unauthorized),
+        }
+    }
+}
+
+pub fn new_token() -> String {
+    return format!("{:032x}", rand::thread_rng().gen::<u128>());
+}
+
+pub fn session_cookie(discussion_id: &str, token: String) -> Cookie<'static> {
+    return Cookie::build((SESSION_COOKIE, token))
+        .path(format!("/discussion/{}", discussion_id))
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .build();
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use rocket::data::{IoHandler, IoStream};
+use rocket::futures::{SinkExt, StreamExt};
+use rocket::http::Status as HttpStatus;
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket::tokio::sync::Notify;
+use rocket::tokio::time;
+use serde_json::{Map, Value};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role as SocketRole;
+use debug_panic::debug_panic;
+
+use super::commands::Command;
+use super::config::Config;
+use super::messages::{ApiError, SocketMessage};
+use super::rate_limit::RateLimiter;
+use super::validation::ValidationError;
+
+// Even with nothing changing, the discussion's clock does, so sockets send
+//  whatever moved at least this often
+const TICK: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    // One per discussion with sockets open, woken whenever the discussion is
+    //  changed so that every socket sends the change straight away
+    static ref WAKERS: Mutex<HashMap<String, Arc<Notify>>> = Mutex::new(HashMap::new());
+}
+
+fn waker(id: &str) -> Arc<Notify> {
+    match WAKERS.lock() {
+        Ok(mut wakers) => return Arc::clone(wakers.entry(id.to_string()).or_default()),
+        Err(e) => {
+            debug_panic!(e.to_string());
+            return Arc::new(Notify::new());
+        }
+    }
+}
+
+pub fn wake(id: &str) {
+    if let Ok(wakers) = WAKERS.lock() {
+        if let Some(waker) = wakers.get(id) {
+            waker.notify_waiters();
+        }
+    }
+}
+
+pub fn forget(id: &str) {
+    if let Ok(mut wakers) = WAKERS.lock() {
+        if let Some(waker) = wakers.remove(id) {
+            waker.notify_waiters();
+        }
+    }
+}
+
+// A control socket for one discussion. It acts with the session the client had
+//  when it connected, so clients need to join before connecting. Commands are
+//  charged to the socket itself, at the same rate as a client's HTTP requests.
+pub struct ControlSocket {
+    id: String,
+    token: Option<String>,
+    config: Config,
+    limiter: RateLimiter,
+}
+
+impl ControlSocket {
+
+    pub fn new(id: String, token: Option<String>, config: Config) -> Self {
+        let limiter: RateLimiter = RateLimiter::new(config.client_rate_limit, Duration::from_secs(60));
+        ControlSocket {
+            id: id,
+            token: token,
+            config: config,
+            limiter: limiter,
+        }
+    }
+
+    fn handle(&self, text: &str) -> SocketMessage {
+        let request: Value = serde_json::from_str(text).unwrap_or(Value::Null);
+        let id: Option<u64> = request.get("id").and_then(Value::as_u64);
+        let result: Result<(), ApiError> = match (self.limiter.check(""), serde_json::from_value::<Command>(request)) {
+            (Err(wait), _) => Err(ApiError::RateLimited(wait)),
+            (Ok(()), Ok(command)) => super::with_discussion(&self.id, |discussion| command.run(discussion, self.token.as_deref())).and_then(|result| result),
+            (Ok(()), Err(e)) => Err(ValidationError::MalformedRequest(e.to_string()).into()),
+        };
+        return SocketMessage::Result {
+            id: id,
+            error: result.err().map(|e| e.into()),
+        };
+    }
+
+    // The fields of the current status report which differ from `sent`, which
+    //  is then brought up to date
+    fn changes(&self, sent: &mut Map<String, Value>) -> Map<String, Value> {
+        let report: Map<String, Value> = match serde_json::to_value(super::generate_status_report(&self.id, &self.config)) {
+            Ok(Value::Object(report)) => report,
+            _ => {
+                debug_panic!();
+                return Map::new();
+            }
+        };
+        return changed_fields(sent, report);
+    }
+
+}
+
+fn changed_fields(sent: &mut Map<String, Value>, report: Map<String, Value>) -> Map<String, Value> {
+    let mut changes: Map<String, Value> = Map::new();
+    for (field, value) in report {
+        if sent.get(&field) != Some(&value) {
+            sent.insert(field.clone(), value.clone());
+            changes.insert(field, value);
+        }
+    }
+    return changes;
+}
+
+async fn send(socket: &mut WebSocketStream<IoStream>, message: &SocketMessage) -> io::Result<()> {
+    let text: String = match serde_json::to_string(message) {
+        Ok(text) => text,
+        Err(e) => {
+            debug_panic!(e.to_string());
+            return Ok(());
+        }
+    };
+    return socket.send(Message::Text(text)).await.map_err(io::Error::other);
+}
+
+#[rocket::async_trait]
+impl IoHandler for ControlSocket {
+    async fn io(self: Pin<Box<Self>>, io: IoStream) -> io::Result<()> {
+
+        let mut socket: WebSocketStream<IoStream> = WebSocketStream::from_raw_socket(io, SocketRole::Server, None).await;
+        let waker: Arc<Notify> = waker(&self.id);
+        let mut ticks = time::interval(TICK);
+        let mut sent: Map<String, Value> = Map::new();
+
+        loop {
+            // Made before looking at the discussion, so a change made while
+            //  we're sending this one still wakes us
+            let woken = waker.notified();
+
+            let changes: Map<String, Value> = self.changes(&mut sent);
+            if !changes.is_empty() {
+                send(&mut socket, &SocketMessage::State { changes: changes }).await?;
+            }
+
+            rocket::tokio::select! {
+                message = socket.next() => match message {
+                    Some(Ok(Message::Text(text))) => send(&mut socket, &self.handle(&text)).await?,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => (),
+                    Some(Err(e)) => return Err(io::Error::other(e)),
+                },
+                _ = woken => (),
+                _ = ticks.tick() => (),
+            }
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ControlSocket {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let is_upgrade: bool = request.headers().get_one("Upgrade").is_some_and(|protocol| protocol.eq_ignore_ascii_case("websocket"));
+        let accept: String = match (is_upgrade, request.headers().get_one("Sec-WebSocket-Key")) {
+            (true, Some(key)) => derive_accept_key(key.as_bytes()),
+            _ => return Err(HttpStatus::UpgradeRequired),
+        };
+        return Response::build()
+            .raw_header("Sec-WebSocket-Accept", accept)
+            .upgrade("websocket", self)
+            .ok();
+    }
+}
+
+#[test]
+fn test_only_changed_fields_are_sent() {
+    let report = |json: Value| match json {
+        Value::Object(report) => report,
+        _ => panic!(),
+    };
+    let mut sent: Map<String, Value> = Map::new();
+    let first = changed_fields(&mut sent, report(serde_json::json!({"status": "Normal", "duration": "0:01"})));
+    assert_eq!(first.len(), 2);
+    let second = changed_fields(&mut sent, report(serde_json::json!({"status": "Normal", "duration": "0:02"})));
+    assert_eq!(Value::Object(second), serde_json::json!({"duration": "0:02"}));
+    assert!(changed_fields(&mut sent, report(serde_json::json!({"status": "Normal", "duration": "0:02"}))).is_empty());
+}
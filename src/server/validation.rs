@@ -0,0 +1,405 @@
+use std::fmt;
+use std::ops::Deref;
+
+use rocket::request::FromParam;
+
+use super::config::Config;
+use crate::discussion::portable::{PortableDiscussion, PortableError};
+use crate::discussion::roster::{Roster, RosterEntry};
+use crate::discussion::speech::SpeechId;
+
+// Everything a client sends us passes through here before it reaches a
+//  `Discussion` or the filesystem. Route parameters use the types below as
+//  `Result<T, ValidationError>` so that a rejection can be explained to the
+//  client rather than just turning into a 404.
+
+pub const MAX_DISCUSSION_ID_LENGTH: usize = 64;
+pub const MAX_SPEAKER_NAME_LENGTH: usize = 64;
+pub const MAX_MOTION_LENGTH: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    EmptyDiscussionId,
+    DiscussionIdTooLong,
+    DiscussionIdHasIllegalCharacter(char),
+    EmptySpeakerName,
+    SpeakerNameTooLong,
+    SpeakerNameHasIllegalCharacter(char),
+    UnknownSpeechType(u8),
+    MalformedSpeechId(String),
+    UnknownPriorityMode(String),
+    UnknownPauseState(String),
+    UnknownSeriesRanking(String),
+    UnknownRole(String),
+    EmptyMotion,
+    MotionTooLong,
+    MotionHasIllegalCharacter(char),
+    MalformedMotionNumber(String),
+    UnknownMotionOutcome(String),
+    MalformedRequest(String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::EmptyDiscussionId => write!(f, "discussion IDs cannot be empty"),
+            ValidationError::DiscussionIdTooLong => write!(f, "discussion IDs can be at most {} characters long", MAX_DISCUSSION_ID_LENGTH),
+            ValidationError::DiscussionIdHasIllegalCharacter(c) => write!(f, "discussion IDs can only contain letters, digits, '-' and '_', not {:?}", c),
+            ValidationError::EmptySpeakerName => write!(f, "speaker names cannot be empty"),
+            ValidationError::SpeakerNameTooLong => write!(f, "speaker names can be at most {} characters long", MAX_SPEAKER_NAME_LENGTH),
+            ValidationError::SpeakerNameHasIllegalCharacter(c) => write!(f, "speaker names cannot contain {:?}", c),
+            ValidationError::UnknownSpeechType(stype) => write!(f, "{} is not a speech type (expected 1 or 2)", stype),
+            ValidationError::MalformedSpeechId(id) => write!(f, "{:?} is not a speech ID", id),
+            ValidationError::UnknownPriorityMode(mode) => write!(f, "{:?} is not a priority mode (expected \"fcfs\", \"brevity\" or \"shy\")", mode),
+            ValidationError::UnknownPauseState(state) => write!(f, "{:?} is not a pause state (expected \"pause\" or \"unpause\")", state),
+            ValidationError::UnknownSeriesRanking(state) => write!(f, "{:?} is not a series ranking (expected \"on\" or \"off\")", state),
+            ValidationError::UnknownRole(role) => write!(f, "{:?} is not a role (expected \"chair\", \"vice_chair\", \"secretary\" or \"member\")", role),
+            ValidationError::EmptyMotion => write!(f, "motions cannot be empty"),
+            ValidationError::MotionTooLong => write!(f, "motions can be at most {} characters long", MAX_MOTION_LENGTH),
+            ValidationError::MotionHasIllegalCharacter(c) => write!(f, "motions cannot contain {:?}", c),
+            ValidationError::MalformedMotionNumber(number) => write!(f, "{:?} is not a motion number", number),
+            ValidationError::UnknownMotionOutcome(outcome) => write!(f, "{:?} is not what can become of a motion (expected \"carried\", \"defeated\" or \"withdrawn\")", outcome),
+            ValidationError::MalformedRequest(e) => write!(f, "malformed request: {}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscussionId<'a>(&'a str);
+
+impl<'a> DiscussionId<'a> {
+    pub fn parse(id: &'a str) -> Result<Self, ValidationError> {
+        if id.is_empty() {
+            return Err(ValidationError::EmptyDiscussionId);
+        }
+        if id.chars().count() > MAX_DISCUSSION_ID_LENGTH {
+            return Err(ValidationError::DiscussionIdTooLong);
+        }
+        if let Some(c) = id.chars().find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_')) {
+            return Err(ValidationError::DiscussionIdHasIllegalCharacter(c));
+        }
+        return Ok(DiscussionId(id));
+    }
+}
+
+impl<'a> Deref for DiscussionId<'a> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        return self.0;
+    }
+}
+
+impl<'a> FromParam<'a> for DiscussionId<'a> {
+    type Error = ValidationError;
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        return DiscussionId::parse(param);
+    }
+}
+
+// A speaker name with surrounding whitespace removed and every internal run of
+//  whitespace collapsed to a single space, so that "Ada  Lovelace " and
+//  "Ada Lovelace" end up as the same speaker. Names are shown in web pages, so
+//  they can't contain anything that looks like a tag either.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeakerName(String);
+
+impl SpeakerName {
+    pub fn parse(name: &str) -> Result<Self, ValidationError> {
+        if let Some(c) = name.chars().find(|c| (c.is_control() && !c.is_whitespace()) || *c == '<' || *c == '>') {
+            return Err(ValidationError::SpeakerNameHasIllegalCharacter(c));
+        }
+        let normalized: String = name.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if normalized.is_empty() {
+            return Err(ValidationError::EmptySpeakerName);
+        }
+        if normalized.chars().count() > MAX_SPEAKER_NAME_LENGTH {
+            return Err(ValidationError::SpeakerNameTooLong);
+        }
+        return Ok(SpeakerName(normalized));
+    }
+
+    pub fn into_string(self) -> String {
+        return self.0;
+    }
+}
+
+impl Deref for SpeakerName {
+    type Target = str;
+    fn deref(&self) -> &str {
+        return &self.0;
+    }
+}
+
+impl<'a> FromParam<'a> for SpeakerName {
+    type Error = ValidationError;
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        return SpeakerName::parse(param);
+    }
+}
+
+// The wording of a motion with surrounding whitespace removed. Motions can run
+//  over several lines, so only other control characters are refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MotionText(String);
+
+impl MotionText {
+    pub fn parse(text: &str) -> Result<Self, ValidationError> {
+        if let Some(c) = text.chars().find(|c| c.is_control() && !c.is_whitespace()) {
+            return Err(ValidationError::MotionHasIllegalCharacter(c));
+        }
+        let trimmed: &str = text.trim();
+        if trimmed.is_empty() {
+            return Err(ValidationError::EmptyMotion);
+        }
+        if trimmed.chars().count() > MAX_MOTION_LENGTH {
+            return Err(ValidationError::MotionTooLong);
+        }
+        return Ok(MotionText(trimmed.to_string()));
+    }
+
+    pub fn into_string(self) -> String {
+        return self.0;
+    }
+}
+
+impl Deref for MotionText {
+    type Target = str;
+    fn deref(&self) -> &str {
+        return &self.0;
+    }
+}
+
+impl<'a> FromParam<'a> for SpeechId {
+    type Error = ValidationError;
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param.parse::<usize>() {
+            Ok(id) => return Ok(SpeechId(id)),
+            Err(_) => return Err(ValidationError::MalformedSpeechId(param.to_string())),
+        }
+    }
+}
+
+// Rosters are held to the same rules as names typed in by participants, and
+//  come out normalized in the same way
+pub fn normalize_roster(roster: Roster) -> Result<Roster, ValidationError> {
+    let mut entries: Vec<RosterEntry> = Vec::new();
+    for entry in roster.entries {
+        let mut aliases: Vec<String> = Vec::new();
+        for alias in &entry.aliases {
+            aliases.push(SpeakerName::parse(alias)?.into_string());
+        }
+        let mut tags: Vec<String> = Vec::new();
+        for tag in &entry.tags {
+            tags.push(SpeakerName::parse(tag)?.into_string());
+        }
+        entries.push(RosterEntry {
+            name: SpeakerName::parse(&entry.name)?.into_string(),
+            aliases: aliases,
+            voting: entry.voting,
+            tags: tags,
+        });
+    }
+    return Ok(Roster { entries: entries });
+}
+
+// An uploaded discussion is held to the same rules as one built up through the
+//  other routes. Speeches refer to their speakers by name, so names have to be
+//  written just as `SpeakerName` would leave them; rosters are only used to
+//  look names up, so they are normalized like any other. Its limits are kept
+//  within this server's.
+pub fn check_portable(mut portable: PortableDiscussion, config: &Config) -> Result<PortableDiscussion, PortableError> {
+
+    let check_name = |name: &str| -> Result<(), PortableError> {
+        match SpeakerName::parse(name) {
+            Ok(parsed) if *parsed == *name => return Ok(()),
+            Ok(parsed) => return Err(PortableError::Invalid(format!("{:?} should be written {:?}", name, &*parsed))),
+            Err(e) => return Err(PortableError::Invalid(format!("{:?} can't be a name: {}", name, e))),
+        }
+    };
+    for speaker in &portable.speakers {
+        check_name(&speaker.name)?;
+        for alias in &speaker.aliases {
+            check_name(alias)?;
+        }
+    }
+    for speech in &portable.speeches {
+        check_name(&speech.given_name)?;
+    }
+    for motion in &portable.motions {
+        check_name(&motion.moved_by)?;
+        match MotionText::parse(&motion.text) {
+            Ok(parsed) if *parsed == *motion.text => (),
+            Ok(parsed) => return Err(PortableError::Invalid(format!("{:?} should be written {:?}", motion.text, &*parsed))),
+            Err(e) => return Err(PortableError::Invalid(format!("{:?} can't be a motion: {}", motion.text, e))),
+        }
+    }
+
+    portable.settings.roster = match normalize_roster(portable.settings.roster) {
+        Ok(roster) => roster,
+        Err(e) => return Err(PortableError::Invalid(format!("the roster: {}", e))),
+    };
+    portable.settings.new_point_time_limit = clamp_time_limit(portable.settings.new_point_time_limit, config.new_point_time_limit);
+    portable.settings.response_time_limit = clamp_time_limit(portable.settings.response_time_limit, config.response_time_limit);
+    // `Config::validate` makes sure at least one speech can be queued
+    let max_queued: usize = config.max_queued_speeches_per_speaker;
+    portable.settings.max_queued_speeches_per_speaker = Some(portable.settings.max_queued_speeches_per_speaker.unwrap_or(max_queued).clamp(1, max_queued));
+
+    // Neither the series nor its totals can be taken on trust, and joining one
+    //  needs its secret, so the discussion has to join its series again
+    portable.settings.series = None;
+
+    return Ok(portable);
+}
+
+// A time limit of zero would end every speech as it started, so is treated as
+//  no limit at all, and no limit can be longer than the server's
+fn clamp_time_limit(uploaded: Option<u64>, configured: Option<u64>) -> Option<u64> {
+    match (uploaded.filter(|limit| *limit > 0), configured) {
+        (Some(uploaded), Some(configured)) => return Some(uploaded.min(configured)),
+        (None, configured) => return configured,
+        (uploaded, None) => return uploaded,
+    }
+}
+
+// Returns true if `stype` is 2, i.e. if the speech is a response
+pub fn parse_speech_type(stype: u8) -> Result<bool, ValidationError> {
+    match stype {
+        1 => return Ok(false),
+        2 => return Ok(true),
+        _ => return Err(ValidationError::UnknownSpeechType(stype)),
+    }
+}
+
+#[test]
+fn test_discussion_ids() {
+    assert_eq!(DiscussionId::parse("weekly-meeting_2").map(|id| id.0), Ok("weekly-meeting_2"));
+    assert_eq!(DiscussionId::parse(""), Err(ValidationError::EmptyDiscussionId));
+    assert_eq!(DiscussionId::parse(&"a".repeat(MAX_DISCUSSION_ID_LENGTH)).map(|id| id.len()), Ok(MAX_DISCUSSION_ID_LENGTH));
+    assert_eq!(DiscussionId::parse(&"a".repeat(MAX_DISCUSSION_ID_LENGTH + 1)), Err(ValidationError::DiscussionIdTooLong));
+}
+
+#[test]
+fn test_hostile_discussion_ids() {
+    for (id, c) in [
+        ("../../etc/passwd", '.'),
+        ("..", '.'),
+        ("a/b", '/'),
+        ("a\\b", '\\'),
+        ("a\0b", '\0'),
+        ("<script>", '<'),
+        ("a b", ' '),
+        ("caf\u{e9}", '\u{e9}'),
+        ("\u{202e}abc", '\u{202e}'),
+    ] {
+        assert_eq!(DiscussionId::parse(id), Err(ValidationError::DiscussionIdHasIllegalCharacter(c)), "{:?}", id);
+    }
+}
+
+#[test]
+fn test_speaker_names_are_normalized() {
+    assert_eq!(SpeakerName::parse("  Ada   Lovelace\t").map(SpeakerName::into_string), Ok("Ada Lovelace".to_string()));
+    assert_eq!(SpeakerName::parse("Zo\u{eb}").map(SpeakerName::into_string), Ok("Zo\u{eb}".to_string()));
+    assert_eq!(SpeakerName::parse(&"\u{e9}".repeat(MAX_SPEAKER_NAME_LENGTH)).map(|name| name.chars().count()), Ok(MAX_SPEAKER_NAME_LENGTH));
+}
+
+#[test]
+fn test_hostile_speaker_names() {
+    assert_eq!(SpeakerName::parse(""), Err(ValidationError::EmptySpeakerName));
+    assert_eq!(SpeakerName::parse(" \t\n "), Err(ValidationError::EmptySpeakerName));
+    assert_eq!(SpeakerName::parse("\u{3000}"), Err(ValidationError::EmptySpeakerName));
+    assert_eq!(SpeakerName::parse(&"a".repeat(MAX_SPEAKER_NAME_LENGTH + 1)), Err(ValidationError::SpeakerNameTooLong));
+    assert_eq!(SpeakerName::parse(&format!("{}{}", "a ".repeat(MAX_SPEAKER_NAME_LENGTH), "a")), Err(ValidationError::SpeakerNameTooLong));
+    assert_eq!(SpeakerName::parse("Bob\0"), Err(ValidationError::SpeakerNameHasIllegalCharacter('\0')));
+    assert_eq!(SpeakerName::parse("Bob\u{1b}[31m"), Err(ValidationError::SpeakerNameHasIllegalCharacter('\u{1b}')));
+    assert_eq!(SpeakerName::parse("<script>alert(1)</script>"), Err(ValidationError::SpeakerNameHasIllegalCharacter('<')));
+    assert_eq!(SpeakerName::parse("Bob<img src=x onerror=alert(1)>"), Err(ValidationError::SpeakerNameHasIllegalCharacter('<')));
+    assert_eq!(SpeakerName::parse("Tom & \"Jerry\"").map(SpeakerName::into_string), Ok("Tom & \"Jerry\"".to_string()));
+}
+
+#[test]
+fn test_motions() {
+    assert_eq!(MotionText::parse("  That the report\nbe adopted ").map(MotionText::into_string), Ok("That the report\nbe adopted".to_string()));
+    assert_eq!(MotionText::parse(" \n "), Err(ValidationError::EmptyMotion));
+    assert_eq!(MotionText::parse(&"a".repeat(MAX_MOTION_LENGTH + 1)), Err(ValidationError::MotionTooLong));
+    assert_eq!(MotionText::parse("That\u{1b}[2J we adjourn"), Err(ValidationError::MotionHasIllegalCharacter('\u{1b}')));
+}
+
+#[test]
+fn test_speech_types() {
+    assert_eq!(parse_speech_type(1), Ok(false));
+    assert_eq!(parse_speech_type(2), Ok(true));
+    assert_eq!(parse_speech_type(0), Err(ValidationError::UnknownSpeechType(0)));
+    assert_eq!(parse_speech_type(3), Err(ValidationError::UnknownSpeechType(3)));
+}
+
+#[test]
+fn test_uploaded_names_are_checked() {
+    use crate::discussion::Discussion;
+    use crate::discussion::series::Series;
+
+    let config: Config = Config::default();
+    let discussion = Discussion::new(config.discussion_settings());
+    discussion.lock().unwrap().add_new_speech("Ada".to_string(), false);
+    let portable: PortableDiscussion = PortableDiscussion::new(&discussion.lock().unwrap());
+    assert_eq!(check_portable(portable.clone(), &config), Ok(portable.clone()));
+
+    let mut markup: PortableDiscussion = portable.clone();
+    markup.speakers[0].aliases.push("<script>alert(1)</script>".to_string());
+    assert!(matches!(check_portable(markup, &config), Err(PortableError::Invalid(_))));
+
+    let mut unnormalized: PortableDiscussion = portable.clone();
+    unnormalized.speeches[0].given_name = " Ada ".to_string();
+    assert!(matches!(check_portable(unnormalized, &config), Err(PortableError::Invalid(_))));
+
+    let mut unnormalized_motion: PortableDiscussion = portable.clone();
+    discussion.lock().unwrap().move_motion(" That we adjourn".to_string(), "Ada".to_string());
+    unnormalized_motion.motions = PortableDiscussion::new(&discussion.lock().unwrap()).motions;
+    assert!(matches!(check_portable(unnormalized_motion, &config), Err(PortableError::Invalid(_))));
+
+    let mut too_long: PortableDiscussion = portable.clone();
+    too_long.speakers[0].name = "a".repeat(MAX_SPEAKER_NAME_LENGTH + 1);
+    assert!(matches!(check_portable(too_long, &config), Err(PortableError::Invalid(_))));
+
+    let mut roster: PortableDiscussion = portable.clone();
+    roster.settings.roster = Roster::from_csv("name,aliases\n  Ada   Lovelace ,Ada\n").unwrap();
+    assert_eq!(check_portable(roster, &config).unwrap().settings.roster.entries[0].name, "Ada Lovelace");
+
+    let mut hostile_roster: PortableDiscussion = portable.clone();
+    hostile_roster.settings.roster = Roster::from_csv("name,aliases\n<b>Ada</b>,Ada\n").unwrap();
+    assert!(matches!(check_portable(hostile_roster, &config), Err(PortableError::Invalid(_))));
+
+    let mut hostile_series: PortableDiscussion = portable.clone();
+    hostile_series.settings.series = Some(Series::new("../../etc/passwd".to_string(), Roster::default()));
+    assert_eq!(check_portable(hostile_series, &config).unwrap().settings.series, None);
+
+    let mut made_up_series: PortableDiscussion = portable.clone();
+    let mut series: Series = Series::new("committee".to_string(), Roster::default());
+    series.meetings = 1000;
+    made_up_series.settings.series = Some(series);
+    assert_eq!(check_portable(made_up_series, &config).unwrap().settings.series, None);
+
+    // Limits the server wouldn't allow are brought back within its own
+    let mut uncapped: PortableDiscussion = portable.clone();
+    uncapped.settings.max_queued_speeches_per_speaker = None;
+    assert_eq!(check_portable(uncapped, &config).unwrap().settings.max_queued_speeches_per_speaker, Some(config.max_queued_speeches_per_speaker));
+    let mut over_cap: PortableDiscussion = portable.clone();
+    over_cap.settings.max_queued_speeches_per_speaker = Some(usize::MAX);
+    assert_eq!(check_portable(over_cap, &config).unwrap().settings.max_queued_speeches_per_speaker, Some(config.max_queued_speeches_per_speaker));
+    let mut no_queue: PortableDiscussion = portable.clone();
+    no_queue.settings.max_queued_speeches_per_speaker = Some(0);
+    assert_eq!(check_portable(no_queue, &config).unwrap().settings.max_queued_speeches_per_speaker, Some(1));
+
+    let mut zero_time: PortableDiscussion = portable.clone();
+    zero_time.settings.new_point_time_limit = Some(0);
+    zero_time.settings.response_time_limit = Some(0);
+    let checked: PortableDiscussion = check_portable(zero_time.clone(), &config).unwrap();
+    assert_eq!((checked.settings.new_point_time_limit, checked.settings.response_time_limit), (None, None));
+    let timed: Config = Config { new_point_time_limit: Some(120), response_time_limit: Some(60), ..Config::default() };
+    let checked: PortableDiscussion = check_portable(zero_time, &timed).unwrap();
+    assert_eq!((checked.settings.new_point_time_limit, checked.settings.response_time_limit), (Some(120), Some(60)));
+    let mut long_time: PortableDiscussion = portable.clone();
+    long_time.settings.new_point_time_limit = Some(u64::MAX);
+    long_time.settings.response_time_limit = Some(30);
+    let checked: PortableDiscussion = check_portable(long_time, &timed).unwrap();
+    assert_eq!((checked.settings.new_point_time_limit, checked.settings.response_time_limit), (Some(120), Some(30)));
+}
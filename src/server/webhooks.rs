@@ -0,0 +1,229 @@
+use std::cmp;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use chrono::prelude::*;
+use debug_panic::debug_panic;
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use sha2::Sha256;
+
+use super::config::Config;
+use crate::discussion::events::Event;
+
+pub const SIGNATURE_HEADER: &str = "X-Speakeasy-Signature";
+
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const LONGEST_RETRY: Duration = Duration::from_secs(60);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    // One queue per configured URL, each emptied by its own thread so that an
+    //  endpoint which is down only holds up its own deliveries. Events are
+    //  dropped until `start` is called.
+    static ref QUEUES: Mutex<Vec<Sender<String>>> = Mutex::new(Vec::new());
+}
+
+// What gets POSTed, e.g. `{"discussion": "board", "sent_at": "...",
+//  "event": "speaker_changed", "speech": 3, "speaker": "Alice"}`
+#[derive(Serialize)]
+struct Payload<'a> {
+    discussion: &'a str,
+    sent_at: String,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+// Receivers check a request came from us by working this out for themselves
+//  over the raw body
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(e) => {
+            debug_panic!(e.to_string());
+            return String::new();
+        }
+    };
+    mac.update(body.as_bytes());
+    return format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+}
+
+pub struct Webhook {
+    url: String,
+    secret: String,
+    attempts: u32,
+    first_retry: Duration,
+}
+
+impl Webhook {
+
+    // Keeps trying until the endpoint accepts `body`, waiting twice as long
+    //  after each failure. Endpoints which refuse it outright (any other 4xx)
+    //  aren't asked again, since they will only refuse it again.
+    pub fn deliver(&self, agent: &ureq::Agent, body: &str) -> Result<(), String> {
+
+        let signature: String = sign(&self.secret, body);
+        let mut wait: Duration = self.first_retry;
+        let mut last_error: String = String::new();
+
+        for attempt in 0..self.attempts {
+            if attempt > 0 {
+                thread::sleep(wait);
+                wait = cmp::min(wait * 2, LONGEST_RETRY);
+            }
+            let response = agent.post(&self.url)
+                .set("Content-Type", "application/json")
+                .set(SIGNATURE_HEADER, &signature)
+                .send_string(body);
+            match response {
+                Ok(_) => return Ok(()),
+                Err(ureq::Error::Status(status, _)) if status < 500 && status != 429 => return Err(format!("refused with status {}", status)),
+                Err(ureq::Error::Status(status, _)) => last_error = format!("failed with status {}", status),
+                Err(e) => last_error = e.to_string(),
+            }
+        }
+
+        return Err(format!("{} after {} attempts", last_error, self.attempts));
+    }
+
+}
+
+pub fn start(config: &Config) {
+
+    let secret: String = config.webhook_secret.clone().unwrap_or_default();
+    let mut queues = match QUEUES.lock() {
+        Ok(queues) => queues,
+        Err(e) => {
+            debug_panic!(e.to_string());
+            return;
+        }
+    };
+
+    for url in &config.webhooks {
+        let webhook: Webhook = Webhook {
+            url: url.clone(),
+            secret: secret.clone(),
+            attempts: config.webhook_attempts,
+            first_retry: FIRST_RETRY,
+        };
+        let (sender, receiver) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            let agent: ureq::Agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+            for body in receiver {
+                if let Err(e) = webhook.deliver(&agent, &body) {
+                    error!("Gave up sending an event to webhook {}: {}", webhook.url, e);
+                }
+            }
+        });
+        queues.push(sender);
+    }
+
+}
+
+pub fn publish(id: &str, events: Vec<Event>) {
+
+    let queues = match QUEUES.lock() {
+        Ok(queues) => queues,
+        Err(e) => {
+            debug_panic!(e.to_string());
+            return;
+        }
+    };
+    if queues.is_empty() {
+        return;
+    }
+
+    for event in &events {
+        let payload: Payload = Payload {
+            discussion: id,
+            sent_at: Utc::now().to_rfc3339(),
+            event: event,
+        };
+        match serde_json::to_string(&payload) {
+            Ok(body) => for queue in queues.iter() {
+                let _ = queue.send(body.clone());
+            },
+            Err(e) => debug_panic!(e.to_string()),
+        }
+    }
+
+}
+
+// Stands in for a receiving server on a local port. It answers each request
+//  with the next of `statuses`, then hands back everything it was sent.
+#[cfg(test)]
+fn stand_in(statuses: Vec<u16>) -> (String, thread::JoinHandle<Vec<String>>) {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url: String = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let mut requests: Vec<String> = Vec::new();
+        for status in statuses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request: String = String::new();
+            let mut content_length: usize = 0;
+            loop {
+                let mut line: String = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(length) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body: Vec<u8> = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+            requests.push(request);
+            write!(stream, "HTTP/1.1 {} Whatever\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+        }
+        return requests;
+    });
+    return (url, server);
+}
+
+#[cfg(test)]
+fn test_webhook(url: String, attempts: u32) -> Webhook {
+    return Webhook {
+        url: url,
+        secret: "hunter2".to_string(),
+        attempts: attempts,
+        first_retry: Duration::from_millis(10),
+    };
+}
+
+#[test]
+fn test_deliveries_are_signed_and_retried() {
+    let agent: ureq::Agent = ureq::agent();
+    let body: &str = r#"{"event":"point_of_order_raised"}"#;
+
+    let (url, server) = stand_in(vec![503, 500, 204]);
+    assert_eq!(test_webhook(url, 3).deliver(&agent, body), Ok(()));
+    let requests: Vec<String> = server.join().unwrap();
+    assert_eq!(requests.len(), 3);
+    let signature_line: String = format!("{}: {}", SIGNATURE_HEADER, sign("hunter2", body)).to_ascii_lowercase();
+    assert!(requests[2].to_ascii_lowercase().contains(&signature_line), "{}", requests[2]);
+    assert!(requests[2].ends_with(body));
+
+    let (url, server) = stand_in(vec![503, 503]);
+    assert!(test_webhook(url, 2).deliver(&agent, body).is_err());
+    assert_eq!(server.join().unwrap().len(), 2);
+
+    let (url, server) = stand_in(vec![404]);
+    assert!(test_webhook(url, 5).deliver(&agent, body).is_err());
+    assert_eq!(server.join().unwrap().len(), 1);
+}
+
+#[test]
+fn test_signatures_match_a_known_value() {
+    // From RFC 4231, test case 2
+    assert_eq!(sign("Jefe", "what do ya want for nothing?"), "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+}
@@ -0,0 +1,296 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::prelude::*;
+use debug_panic::debug_panic;
+use serde::Serialize;
+
+use crate::discussion::{Discussion, Lifecycle, PriorityMode};
+use crate::discussion::motion::MotionOutcome;
+use crate::discussion::speech::{Speaker, Speech, SpeechId};
+use crate::discussion::series::Series;
+
+pub mod history;
+pub mod memory;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum SpeechPosition {
+    Past,
+    Current,
+    Upcoming,
+}
+
+#[derive(Serialize)]
+pub struct SpeakerRecord {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub total_speaking_time: u64,
+    pub number_of_speeches_given: u16,
+}
+
+/// `stype` follows the same convention as `NewSpeakerRequest`: 1 is a new point
+///  and 2 is a response
+#[derive(Serialize)]
+pub struct SpeechRecord {
+    pub id: usize,
+    pub speaker: String,
+    pub stype: u8,
+    pub queued_at: String,
+    pub duration: u64,
+    pub position: SpeechPosition,
+}
+
+/// Motions are listed in the order they were moved, which is what numbers them
+#[derive(Serialize)]
+pub struct MotionRecord {
+    pub text: String,
+    pub moved_by: String,
+    pub moved_at: String,
+    pub during_speech: Option<usize>,
+    pub outcome: Option<MotionOutcome>,
+    pub resolved_at: Option<String>,
+}
+
+/// A plain snapshot of a discussion which can be written to disk once it no
+///  longer needs to live in memory. All durations are in whole seconds.
+#[derive(Serialize)]
+pub struct DiscussionRecord<'a> {
+    pub id: &'a str,
+    pub archived_at: String,
+    pub lifecycle: Lifecycle,
+    pub priority_mode: &'a PriorityMode,
+    pub duration: u64,
+    pub speakers: Vec<SpeakerRecord>,
+    pub speeches: Vec<SpeechRecord>,
+    pub motions: Vec<MotionRecord>,
+}
+
+impl<'a> DiscussionRecord<'a> {
+
+    pub fn new(id: &'a str, discussion: &'a Discussion) -> Self {
+
+        // Every alias of a speaker maps to the same `Arc`, so we need to make
+        //  sure each speaker only gets recorded once
+        let mut seen: Vec<&Arc<Mutex<Speaker>>> = Vec::new();
+        let mut speakers: Vec<SpeakerRecord> = Vec::new();
+        for speaker_arc in discussion.speakers.values() {
+            if seen.iter().any(|s| Arc::ptr_eq(s, speaker_arc)) {
+                continue;
+            }
+            seen.push(speaker_arc);
+            match speaker_arc.lock() {
+                Ok(speaker) => {
+                    let mut aliases: Vec<String> = speaker.aliases.iter().cloned().collect();
+                    aliases.sort();
+                    speakers.push(SpeakerRecord {
+                        name: speaker.name.clone(),
+                        aliases: aliases,
+                        total_speaking_time: speaker.total_speaking_time.as_secs(),
+                        number_of_speeches_given: speaker.number_of_speeches_given,
+                    });
+                },
+                Err(e) => debug_panic!(e.to_string()),
+            }
+        }
+        speakers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut speeches: Vec<SpeechRecord> = Vec::new();
+        let mut record = |id: &SpeechId, stype: u8, position: SpeechPosition| {
+            let speech: &Speech = match discussion.speeches.get(*id) {
+                Some(speech) => speech,
+                None => {
+                    debug_panic!();
+                    return;
+                }
+            };
+            match speech.speaker.lock() {
+                Ok(speaker) => speeches.push(SpeechRecord {
+                    id: speech.id.0,
+                    speaker: speaker.name.clone(),
+                    stype: stype,
+                    queued_at: speech.queued_at.to_rfc3339(),
+                    duration: speech.duration.as_secs(),
+                    position: position,
+                }),
+                Err(e) => debug_panic!(e.to_string()),
+            }
+        };
+
+        for (new_point, responses) in &discussion.past_speeches {
+            record(new_point, 1, SpeechPosition::Past);
+            for response in responses {
+                record(response, 2, SpeechPosition::Past);
+            }
+        }
+
+        // Whichever speech is at the front of the current block is the one
+        //  being given right now
+        let mut is_current: bool = true;
+        if let Some(current_new_point) = &discussion.current_new_point {
+            record(current_new_point, 1, SpeechPosition::Current);
+            is_current = false;
+        }
+        for response in &discussion.first_response_block {
+            record(response, 2, if is_current { SpeechPosition::Current } else { SpeechPosition::Upcoming });
+            is_current = false;
+        }
+
+        for (new_point, responses) in &discussion.upcoming_speeches {
+            record(new_point, 1, SpeechPosition::Upcoming);
+            for response in responses {
+                record(response, 2, SpeechPosition::Upcoming);
+            }
+        }
+
+        return DiscussionRecord {
+            id: id,
+            archived_at: Utc::now().to_rfc3339(),
+            lifecycle: discussion.lifecycle,
+            priority_mode: discussion.get_priority_mode(),
+            duration: discussion.duration.as_secs(),
+            speakers: speakers,
+            speeches: speeches,
+            motions: discussion.motions.iter().map(|motion| MotionRecord {
+                text: motion.text.clone(),
+                moved_by: motion.moved_by.clone(),
+                moved_at: motion.moved_at.to_rfc3339(),
+                during_speech: motion.during_speech.map(|id| id.0),
+                outcome: motion.outcome,
+                resolved_at: motion.resolved_at.map(|resolved_at| resolved_at.to_rfc3339()),
+            }).collect(),
+        };
+
+    }
+
+}
+
+// Discussion IDs come straight from the URL, so anything that isn't obviously
+//  safe in a file name gets replaced before we touch the filesystem
+fn file_stem(id: &str) -> String {
+    return id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+}
+
+/// Writes the discussion to `data_dir` and returns the path of the new file.
+///  The timestamp in the file name means an ID can be reused after its
+///  discussion has been archived without clobbering the older record.
+pub fn write_archive(data_dir: &Path, id: &str, discussion: &Discussion) -> io::Result<PathBuf> {
+
+    // The archive records each speaker's totals, so this is the last chance to
+    //  notice them disagreeing with the speeches
+    if let Err(e) = discussion.check_speaker_totals() {
+        debug_panic!(e);
+    }
+
+    let json: String = match serde_json::to_string_pretty(&DiscussionRecord::new(id, discussion)) {
+        Ok(json) => json,
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    };
+
+    fs::create_dir_all(data_dir)?;
+
+    let stem: String = format!("{}-{}", file_stem(id), Utc::now().format("%Y%m%dT%H%M%S%.3f"));
+    let tmp_path: PathBuf = data_dir.join(format!("{}.json.tmp", stem));
+    let path: PathBuf = data_dir.join(format!("{}.json", stem));
+
+    // Writing to a temporary file first means a crash part way through can
+    //  never leave a truncated archive behind
+    fs::write(&tmp_path, json)?;
+    fs::rename(&tmp_path, &path)?;
+
+    return Ok(path);
+}
+
+fn series_path(data_dir: &Path, name: &str) -> PathBuf {
+    return data_dir.join("series").join(format!("{}.json", file_stem(name)));
+}
+
+/// Reads the series called `name` from `data_dir`, or returns `None` if there
+///  isn't one
+pub fn read_series(data_dir: &Path, name: &str) -> io::Result<Option<Series>> {
+    let json: String = match fs::read_to_string(series_path(data_dir, name)) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match serde_json::from_str(&json) {
+        Ok(series) => return Ok(Some(series)),
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+/// Writes the series to `data_dir`, replacing whatever was there under its
+///  name, and returns the path of the file
+pub fn write_series(data_dir: &Path, series: &Series) -> io::Result<PathBuf> {
+
+    let json: String = match serde_json::to_string_pretty(series) {
+        Ok(json) => json,
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    };
+
+    let path: PathBuf = series_path(data_dir, &series.name);
+    let tmp_path: PathBuf = path.with_extension("json.tmp");
+    if let Some(series_dir) = path.parent() {
+        fs::create_dir_all(series_dir)?;
+    }
+
+    // As with archives, a crash part way through mustn't leave the totals of
+    //  every earlier meeting truncated
+    fs::write(&tmp_path, json)?;
+    fs::rename(&tmp_path, &path)?;
+
+    return Ok(path);
+}
+
+fn series_secret_path(data_dir: &Path, name: &str) -> PathBuf {
+    return data_dir.join("series").join(format!("{}.secret", file_stem(name)));
+}
+
+/// Reads the secret which lets chairs join the series called `name`, or
+///  returns `None` if it hasn't been given one
+pub fn read_series_secret(data_dir: &Path, name: &str) -> io::Result<Option<String>> {
+    match fs::read_to_string(series_secret_path(data_dir, name)) {
+        Ok(secret) => return Ok(Some(secret.trim().to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    }
+}
+
+/// Writes the series' secret next to it. It is kept out of the series itself,
+///  which is handed out to whoever exports a discussion.
+pub fn write_series_secret(data_dir: &Path, name: &str, secret: &str) -> io::Result<PathBuf> {
+
+    let path: PathBuf = series_secret_path(data_dir, name);
+    let tmp_path: PathBuf = path.with_extension("secret.tmp");
+    if let Some(series_dir) = path.parent() {
+        fs::create_dir_all(series_dir)?;
+    }
+    fs::write(&tmp_path, secret)?;
+    fs::rename(&tmp_path, &path)?;
+
+    return Ok(path);
+}
+
+#[test]
+fn test_file_stem() {
+    assert_eq!(file_stem("weekly-meeting_2"), "weekly-meeting_2");
+    assert_eq!(file_stem("../../etc/passwd"), "______etc_passwd");
+    assert_eq!(file_stem("a b.c"), "a_b_c");
+}
+
+#[test]
+fn test_series_round_trip() {
+    let data_dir: PathBuf = std::env::temp_dir().join(format!("speakeasy-test-series-storage-{}", std::process::id()));
+    assert!(read_series(&data_dir, "committee").unwrap().is_none());
+    let mut series: Series = Series::new("committee".to_string(), crate::discussion::roster::Roster::default());
+    series.meetings = 3;
+    write_series(&data_dir, &series).unwrap();
+    assert_eq!(read_series(&data_dir, "committee").unwrap(), Some(series));
+    assert!(read_series_secret(&data_dir, "committee").unwrap().is_none());
+    write_series_secret(&data_dir, "committee", "hunter2").unwrap();
+    assert_eq!(read_series_secret(&data_dir, "committee").unwrap(), Some("hunter2".to_string()));
+    fs::remove_dir_all(&data_dir).unwrap();
+}
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::prelude::*;
+
+use super::{DiscussionRecord, SpeakerRecord, SpeechPosition, SpeechRecord};
+use crate::discussion::{Discussion, Lifecycle, PriorityMode};
+use crate::discussion::motion::MotionOutcome;
+
+/// A discussion as the history stores it. The same ID can be used again once a
+///  discussion has been archived, so discussions are told apart by the `key`
+///  their store hands out instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscussionRow {
+    pub id: String,
+    pub opened_at: DateTime<Utc>,
+    pub lifecycle: Lifecycle,
+    pub priority_mode: PriorityMode,
+    pub paused: bool,
+    pub duration: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerRow {
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+/// `speaker` is the name of the speaker the speech belongs to now, which may not
+///  be the name it was added under. `started_at` is when it was first given the
+///  floor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeechRow {
+    pub id: usize,
+    pub speaker: String,
+    pub is_response: bool,
+    pub queued_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub duration: u64,
+    pub position: SpeechPosition,
+}
+
+/// A point of order: the discussion being paused, and later resumed. Numbered
+///  from zero within each discussion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterruptionRow {
+    pub number: usize,
+    pub raised_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub during_speech: Option<usize>,
+}
+
+/// A motion and what became of it, numbered from zero within each discussion
+///  like the discussion numbers them. `outcome` and `resolved_at` are left
+///  empty while it is still before the meeting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotionRow {
+    pub number: usize,
+    pub text: String,
+    pub moved_by: String,
+    pub moved_at: DateTime<Utc>,
+    pub during_speech: Option<usize>,
+    pub outcome: Option<MotionOutcome>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+pub type DiscussionKey = i64;
+
+/// How much one person spoke across the discussions opened in a period. People
+///  are matched up between discussions by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerTotal {
+    pub name: String,
+    pub discussions: u32,
+    pub speeches: u32,
+    pub speaking_time: u64,
+}
+
+/// How responses went in one discussion: how many were given, how long they
+///  took on average, and how long they waited for the floor on average. Both
+///  averages are in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseTimes {
+    pub discussion: String,
+    pub opened_at: DateTime<Utc>,
+    pub responses: u32,
+    pub average_length: f64,
+    pub average_wait: Option<f64>,
+}
+
+/// A motion along with the discussion it was moved in
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovedMotion {
+    pub discussion: String,
+    pub opened_at: DateTime<Utc>,
+    pub motion: MotionRow,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreError(pub String);
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the discussion history could not be used: {}", self.0)
+    }
+}
+
+/// Somewhere to keep the history of every discussion, one row at a time.
+///  Writes only ever come from a `Recorder`, which works out what changed.
+pub trait Store: Send {
+    fn open_discussion(&mut self, row: &DiscussionRow) -> Result<DiscussionKey, StoreError>;
+    fn update_discussion(&mut self, key: DiscussionKey, row: &DiscussionRow) -> Result<(), StoreError>;
+    fn put_speaker(&mut self, key: DiscussionKey, row: &SpeakerRow) -> Result<(), StoreError>;
+    fn remove_speaker(&mut self, key: DiscussionKey, name: &str) -> Result<(), StoreError>;
+    fn put_speech(&mut self, key: DiscussionKey, row: &SpeechRow) -> Result<(), StoreError>;
+    fn remove_speech(&mut self, key: DiscussionKey, id: usize) -> Result<(), StoreError>;
+    fn put_interruption(&mut self, key: DiscussionKey, row: &InterruptionRow) -> Result<(), StoreError>;
+    fn put_motion(&mut self, key: DiscussionKey, row: &MotionRow) -> Result<(), StoreError>;
+    fn remove_motion(&mut self, key: DiscussionKey, number: usize) -> Result<(), StoreError>;
+
+    /// Everyone who has spoken in discussions opened from `from` up to (but not
+    ///  including) `until`, most speaking time first
+    fn speaker_totals(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<SpeakerTotal>, StoreError>;
+    /// Every discussion opened from `from` up to `until` with at least one
+    ///  response given, oldest first
+    fn response_times(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ResponseTimes>, StoreError>;
+    /// Every motion moved in discussions opened from `from` up to `until`, in
+    ///  the order they were moved
+    fn motions(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<MovedMotion>, StoreError>;
+}
+
+/// Everything the history keeps about a discussion, copied out of it while it
+///  is locked so that the rows can be worked out and written once it isn't.
+///  Times are taken from when the copy was made rather than when it is
+///  written.
+pub struct DiscussionState {
+    pub taken_at: DateTime<Utc>,
+    pub lifecycle: Lifecycle,
+    pub priority_mode: PriorityMode,
+    pub paused: bool,
+    pub duration: u64,
+    pub speakers: Vec<SpeakerRecord>,
+    pub speeches: Vec<SpeechRecord>,
+    pub motions: Vec<MotionRow>,
+}
+
+impl DiscussionState {
+
+    pub fn take(discussion: &Discussion) -> Self {
+        let record: DiscussionRecord = DiscussionRecord::new("", discussion);
+        return DiscussionState {
+            taken_at: Utc::now(),
+            lifecycle: discussion.lifecycle,
+            priority_mode: *discussion.get_priority_mode(),
+            paused: discussion.paused,
+            duration: record.duration,
+            speakers: record.speakers,
+            speeches: record.speeches,
+            motions: discussion.motions.iter().enumerate().map(|(number, motion)| MotionRow {
+                number: number,
+                text: motion.text.clone(),
+                moved_by: motion.moved_by.clone(),
+                moved_at: motion.moved_at,
+                during_speech: motion.during_speech.map(|id| id.0),
+                outcome: motion.outcome,
+                resolved_at: motion.resolved_at,
+            }).collect(),
+        };
+    }
+
+}
+
+// What was last written for one discussion
+struct Recorded {
+    key: DiscussionKey,
+    discussion: DiscussionRow,
+    speakers: HashMap<String, SpeakerRow>,
+    speeches: HashMap<usize, SpeechRow>,
+    interruptions: Vec<InterruptionRow>,
+    motions: Vec<MotionRow>,
+}
+
+/// Keeps a store up to date with discussions as they change. Each call to
+///  `record` compares the discussion with what was written last time and only
+///  writes the rows which differ, so it can be called after every change (and
+///  every so often for the clock) without rewriting whole discussions.
+pub struct Recorder {
+    store: Box<dyn Store>,
+    discussions: HashMap<String, Recorded>,
+}
+
+fn parse_time(time: &str) -> DateTime<Utc> {
+    return DateTime::parse_from_rfc3339(time).map(|time| time.with_timezone(&Utc)).unwrap_or_else(|_| Utc::now());
+}
+
+impl Recorder {
+
+    pub fn new(store: Box<dyn Store>) -> Self {
+        Recorder {
+            store: store,
+            discussions: HashMap::new(),
+        }
+    }
+
+    pub fn store(&self) -> &dyn Store {
+        return self.store.as_ref();
+    }
+
+    pub fn record(&mut self, id: &str, record: DiscussionState) -> Result<(), StoreError> {
+
+        let now: DateTime<Utc> = record.taken_at;
+        let Recorder { store, discussions } = self;
+
+        let mut discussion_row: DiscussionRow = DiscussionRow {
+            id: id.to_string(),
+            opened_at: now,
+            lifecycle: record.lifecycle,
+            priority_mode: record.priority_mode,
+            paused: record.paused,
+            duration: record.duration,
+        };
+        let recorded: &mut Recorded = match discussions.get_mut(id) {
+            Some(recorded) => {
+                discussion_row.opened_at = recorded.discussion.opened_at;
+                recorded
+            },
+            None => {
+                let key: DiscussionKey = store.open_discussion(&discussion_row)?;
+                discussions.entry(id.to_string()).or_insert(Recorded {
+                    key: key,
+                    discussion: discussion_row.clone(),
+                    speakers: HashMap::new(),
+                    speeches: HashMap::new(),
+                    interruptions: Vec::new(),
+                    motions: Vec::new(),
+                })
+            }
+        };
+        let key: DiscussionKey = recorded.key;
+
+        if recorded.discussion != discussion_row {
+            store.update_discussion(key, &discussion_row)?;
+            recorded.discussion = discussion_row;
+        }
+
+        for speaker in &record.speakers {
+            let row: SpeakerRow = SpeakerRow { name: speaker.name.clone(), aliases: speaker.aliases.clone() };
+            if recorded.speakers.get(&row.name) != Some(&row) {
+                store.put_speaker(key, &row)?;
+                recorded.speakers.insert(row.name.clone(), row);
+            }
+        }
+
+        let mut current_speech: Option<usize> = None;
+        for speech in &record.speeches {
+            let previous: Option<&SpeechRow> = recorded.speeches.get(&speech.id);
+            if speech.position == SpeechPosition::Current {
+                current_speech = Some(speech.id);
+            }
+            let row: SpeechRow = SpeechRow {
+                id: speech.id,
+                speaker: speech.speaker.clone(),
+                is_response: speech.stype == 2,
+                queued_at: previous.map_or_else(|| parse_time(&speech.queued_at), |previous| previous.queued_at),
+                // A speech only starts once, however often the chair goes back
+                started_at: match (previous.and_then(|previous| previous.started_at), &speech.position) {
+                    (Some(started_at), _) => Some(started_at),
+                    (None, SpeechPosition::Upcoming) => None,
+                    (None, _) => Some(now),
+                },
+                duration: speech.duration,
+                position: speech.position.clone(),
+            };
+            if previous != Some(&row) {
+                store.put_speech(key, &row)?;
+                recorded.speeches.insert(row.id, row);
+            }
+        }
+
+        // Withdrawn speeches, and speakers who have been aliased to somebody
+        //  else, are gone from the discussion
+        let withdrawn: Vec<usize> = recorded.speeches.keys().copied().filter(|id| !record.speeches.iter().any(|speech| speech.id == *id)).collect();
+        for id in withdrawn {
+            store.remove_speech(key, id)?;
+            recorded.speeches.remove(&id);
+        }
+        let merged: Vec<String> = recorded.speakers.keys().filter(|name| !record.speakers.iter().any(|speaker| speaker.name == **name)).cloned().collect();
+        for name in merged {
+            store.remove_speaker(key, &name)?;
+            recorded.speakers.remove(&name);
+        }
+
+        let is_open: bool = recorded.interruptions.last().is_some_and(|interruption| interruption.resolved_at.is_none());
+        let interruption: Option<InterruptionRow> = match (record.paused, recorded.interruptions.last()) {
+            (true, _) if !is_open => Some(InterruptionRow {
+                number: recorded.interruptions.len(),
+                raised_at: now,
+                resolved_at: None,
+                during_speech: current_speech,
+            }),
+            (false, Some(open)) if is_open => Some(InterruptionRow { resolved_at: Some(now), ..open.clone() }),
+            _ => None,
+        };
+        if let Some(interruption) = interruption {
+            store.put_interruption(key, &interruption)?;
+            if is_open {
+                recorded.interruptions.pop();
+            }
+            recorded.interruptions.push(interruption);
+        }
+
+        for motion in &record.motions {
+            if recorded.motions.get(motion.number) != Some(motion) {
+                store.put_motion(key, motion)?;
+                match recorded.motions.get_mut(motion.number) {
+                    Some(previous) => *previous = motion.clone(),
+                    None => recorded.motions.push(motion.clone()),
+                }
+            }
+        }
+        // Only undoing takes motions away, and then always the latest
+        while recorded.motions.len() > record.motions.len() {
+            store.remove_motion(key, recorded.motions.len() - 1)?;
+            recorded.motions.pop();
+        }
+
+        return Ok(());
+    }
+
+    /// Stops following a discussion once it has been archived, so that its ID
+    ///  can start a new discussion in the history
+    pub fn forget(&mut self, id: &str) {
+        self.discussions.remove(id);
+    }
+
+}
+
+// Runs a few meetings through `store` and checks what the history makes of
+//  them, so that every store is held to the same standard
+#[cfg(test)]
+pub fn check_store(store: Box<dyn Store>) {
+
+    use std::sync::{Arc, Mutex};
+    use crate::discussion::DiscussionSettings;
+
+    let mut recorder: Recorder = Recorder::new(store);
+
+    let board: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut board = board.lock().unwrap();
+    board.add_new_speech("Ada".to_string(), false);
+    recorder.record("board", DiscussionState::take(&board)).unwrap();
+    board.add_new_speech("Alan".to_string(), true);
+    board.add_new_speech("Bea".to_string(), true);
+    board.add_new_speech("Beatrice".to_string(), false);
+    recorder.record("board", DiscussionState::take(&board)).unwrap();
+    for _ in 0..3 {
+        board.tick_clock();
+    }
+    board.goto_next_speech();
+    board.tick_clock();
+    board.set_paused(true);
+    recorder.record("board", DiscussionState::take(&board)).unwrap();
+    board.set_paused(false);
+    board.alias_speakers(&"Bea".to_string(), &"Beatrice".to_string());
+    board.add_chair("chair".to_string());
+    board.withdraw_speech_as("chair", crate::discussion::speech::SpeechId(2));
+    recorder.record("board", DiscussionState::take(&board)).unwrap();
+    board.move_motion("That the minutes be approved".to_string(), "Alan".to_string());
+    recorder.record("board", DiscussionState::take(&board)).unwrap();
+    board.resolve_motion(0, MotionOutcome::Carried);
+    board.move_motion("That we adjourn".to_string(), "Ada".to_string());
+    recorder.record("board", DiscussionState::take(&board)).unwrap();
+    // Taking a motion back takes its row with it
+    board.undo();
+    board.close();
+    recorder.record("board", DiscussionState::take(&board)).unwrap();
+    recorder.forget("board");
+
+    // The same ID again is a different meeting
+    let board_again: Arc<Mutex<Discussion>> = Discussion::new(DiscussionSettings::default());
+    let mut board_again = board_again.lock().unwrap();
+    board_again.add_new_speech("Ada".to_string(), false);
+    board_again.tick_clock();
+    recorder.record("board", DiscussionState::take(&board_again)).unwrap();
+
+    let all_time = |store: &dyn Store| store.speaker_totals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap();
+    let totals: Vec<SpeakerTotal> = all_time(recorder.store());
+    assert_eq!(totals, vec![
+        SpeakerTotal { name: "Ada".to_string(), discussions: 2, speeches: 2, speaking_time: 4 },
+        SpeakerTotal { name: "Alan".to_string(), discussions: 1, speeches: 1, speaking_time: 1 },
+    ]);
+
+    let response_times: Vec<ResponseTimes> = recorder.store().response_times(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap();
+    assert_eq!(response_times.len(), 1);
+    assert_eq!(response_times[0].discussion, "board");
+    assert_eq!(response_times[0].responses, 1);
+    assert_eq!(response_times[0].average_length, 1.0);
+    assert!(response_times[0].average_wait.is_some_and(|wait| (0.0..5.0).contains(&wait)));
+
+    let motions: Vec<MovedMotion> = recorder.store().motions(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap();
+    assert_eq!(motions.len(), 1);
+    assert_eq!(motions[0].discussion, "board");
+    assert_eq!((motions[0].motion.number, motions[0].motion.text.as_str(), motions[0].motion.moved_by.as_str()), (0, "That the minutes be approved", "Alan"));
+    assert_eq!(motions[0].motion.during_speech, Some(1));
+    assert_eq!(motions[0].motion.outcome, Some(MotionOutcome::Carried));
+    assert!(motions[0].motion.resolved_at.is_some_and(|resolved_at| resolved_at >= motions[0].motion.moved_at));
+
+    let later: DateTime<Utc> = Utc::now() + chrono::Duration::hours(1);
+    assert_eq!(recorder.store().speaker_totals(later, DateTime::<Utc>::MAX_UTC).unwrap(), Vec::new());
+    assert_eq!(recorder.store().motions(later, DateTime::<Utc>::MAX_UTC).unwrap(), Vec::new());
+}
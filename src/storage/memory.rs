@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::prelude::*;
+
+use super::SpeechPosition;
+use super::history::*;
+
+/// Keeps the history in memory, for tests and for trying things out. Everything
+///  is lost when it is dropped.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    pub discussions: Vec<DiscussionRow>,
+    pub speakers: BTreeMap<(DiscussionKey, String), SpeakerRow>,
+    pub speeches: BTreeMap<(DiscussionKey, usize), SpeechRow>,
+    pub interruptions: BTreeMap<(DiscussionKey, usize), InterruptionRow>,
+    pub motions: BTreeMap<(DiscussionKey, usize), MotionRow>,
+}
+
+impl MemoryStore {
+
+    fn discussion(&mut self, key: DiscussionKey) -> Result<&mut DiscussionRow, StoreError> {
+        match usize::try_from(key).ok().and_then(|i| self.discussions.get_mut(i)) {
+            Some(discussion) => return Ok(discussion),
+            None => return Err(StoreError(format!("there is no discussion {}", key))),
+        }
+    }
+
+    // The speeches given in discussions opened in the period, with the key of
+    //  the discussion each one is from
+    fn given_speeches(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> impl Iterator<Item = (DiscussionKey, &SpeechRow)> {
+        return self.speeches.iter()
+            .filter(move |((key, _), _)| self.discussions.get(*key as usize).is_some_and(|discussion| from <= discussion.opened_at && discussion.opened_at < until))
+            .filter(|(_, speech)| speech.position != SpeechPosition::Upcoming)
+            .map(|((key, _), speech)| (*key, speech));
+    }
+
+}
+
+impl Store for MemoryStore {
+
+    fn open_discussion(&mut self, row: &DiscussionRow) -> Result<DiscussionKey, StoreError> {
+        self.discussions.push(row.clone());
+        return Ok(self.discussions.len() as DiscussionKey - 1);
+    }
+
+    fn update_discussion(&mut self, key: DiscussionKey, row: &DiscussionRow) -> Result<(), StoreError> {
+        *self.discussion(key)? = row.clone();
+        return Ok(());
+    }
+
+    fn put_speaker(&mut self, key: DiscussionKey, row: &SpeakerRow) -> Result<(), StoreError> {
+        self.speakers.insert((key, row.name.clone()), row.clone());
+        return Ok(());
+    }
+
+    fn remove_speaker(&mut self, key: DiscussionKey, name: &str) -> Result<(), StoreError> {
+        self.speakers.remove(&(key, name.to_string()));
+        return Ok(());
+    }
+
+    fn put_speech(&mut self, key: DiscussionKey, row: &SpeechRow) -> Result<(), StoreError> {
+        self.speeches.insert((key, row.id), row.clone());
+        return Ok(());
+    }
+
+    fn remove_speech(&mut self, key: DiscussionKey, id: usize) -> Result<(), StoreError> {
+        self.speeches.remove(&(key, id));
+        return Ok(());
+    }
+
+    fn put_interruption(&mut self, key: DiscussionKey, row: &InterruptionRow) -> Result<(), StoreError> {
+        self.interruptions.insert((key, row.number), row.clone());
+        return Ok(());
+    }
+
+    fn put_motion(&mut self, key: DiscussionKey, row: &MotionRow) -> Result<(), StoreError> {
+        self.motions.insert((key, row.number), row.clone());
+        return Ok(());
+    }
+
+    fn remove_motion(&mut self, key: DiscussionKey, number: usize) -> Result<(), StoreError> {
+        self.motions.remove(&(key, number));
+        return Ok(());
+    }
+
+    fn speaker_totals(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<SpeakerTotal>, StoreError> {
+        let mut totals: HashMap<&str, (HashSet<DiscussionKey>, u32, u64)> = HashMap::new();
+        for (key, speech) in self.given_speeches(from, until) {
+            let (discussions, speeches, speaking_time) = totals.entry(&speech.speaker).or_default();
+            discussions.insert(key);
+            *speeches += 1;
+            *speaking_time += speech.duration;
+        }
+        let mut totals: Vec<SpeakerTotal> = totals.into_iter().map(|(name, (discussions, speeches, speaking_time))| SpeakerTotal {
+            name: name.to_string(),
+            discussions: discussions.len() as u32,
+            speeches: speeches,
+            speaking_time: speaking_time,
+        }).collect();
+        totals.sort_by(|a, b| b.speaking_time.cmp(&a.speaking_time).then_with(|| a.name.cmp(&b.name)));
+        return Ok(totals);
+    }
+
+    fn response_times(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ResponseTimes>, StoreError> {
+        let mut responses: BTreeMap<DiscussionKey, Vec<&SpeechRow>> = BTreeMap::new();
+        for (key, speech) in self.given_speeches(from, until).filter(|(_, speech)| speech.is_response) {
+            responses.entry(key).or_default().push(speech);
+        }
+        let mut times: Vec<ResponseTimes> = responses.into_iter().map(|(key, speeches)| {
+            let waits: Vec<f64> = speeches.iter()
+                .filter_map(|speech| speech.started_at.map(|started_at| (started_at - speech.queued_at).num_milliseconds() as f64 / 1000.0))
+                .collect();
+            let discussion: &DiscussionRow = &self.discussions[key as usize];
+            return ResponseTimes {
+                discussion: discussion.id.clone(),
+                opened_at: discussion.opened_at,
+                responses: speeches.len() as u32,
+                average_length: speeches.iter().map(|speech| speech.duration as f64).sum::<f64>() / speeches.len() as f64,
+                average_wait: if waits.is_empty() { None } else { Some(waits.iter().sum::<f64>() / waits.len() as f64) },
+            };
+        }).collect();
+        times.sort_by_key(|time| time.opened_at);
+        return Ok(times);
+    }
+
+    fn motions(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<MovedMotion>, StoreError> {
+        let mut motions: Vec<MovedMotion> = self.motions.iter()
+            .filter_map(|((key, _), motion)| {
+                let discussion: &DiscussionRow = self.discussions.get(*key as usize)?;
+                if discussion.opened_at < from || until <= discussion.opened_at {
+                    return None;
+                }
+                return Some(MovedMotion {
+                    discussion: discussion.id.clone(),
+                    opened_at: discussion.opened_at,
+                    motion: motion.clone(),
+                });
+            })
+            .collect();
+        // The rows are already in order of discussion and number, which breaks
+        //  any ties
+        motions.sort_by_key(|moved| moved.motion.moved_at);
+        return Ok(motions);
+    }
+
+}
+
+#[test]
+fn test_memory_store() {
+    check_store(Box::new(MemoryStore::default()));
+}
@@ -0,0 +1,270 @@
+use std::path::Path;
+
+use chrono::prelude::*;
+use rusqlite::{params, Connection};
+
+use super::history::*;
+use crate::discussion::motion::MotionOutcome;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS discussions (
+        key INTEGER PRIMARY KEY,
+        id TEXT NOT NULL,
+        opened_at TEXT NOT NULL,
+        lifecycle TEXT NOT NULL,
+        priority_mode TEXT NOT NULL,
+        paused INTEGER NOT NULL,
+        duration INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS discussions_by_opened_at ON discussions (opened_at);
+    CREATE TABLE IF NOT EXISTS speakers (
+        discussion INTEGER NOT NULL REFERENCES discussions (key),
+        name TEXT NOT NULL,
+        PRIMARY KEY (discussion, name)
+    );
+    CREATE TABLE IF NOT EXISTS aliases (
+        discussion INTEGER NOT NULL REFERENCES discussions (key),
+        alias TEXT NOT NULL,
+        speaker TEXT NOT NULL,
+        PRIMARY KEY (discussion, alias)
+    );
+    CREATE TABLE IF NOT EXISTS speeches (
+        discussion INTEGER NOT NULL REFERENCES discussions (key),
+        id INTEGER NOT NULL,
+        speaker TEXT NOT NULL,
+        is_response INTEGER NOT NULL,
+        queued_at TEXT NOT NULL,
+        started_at TEXT,
+        duration INTEGER NOT NULL,
+        position TEXT NOT NULL,
+        PRIMARY KEY (discussion, id)
+    );
+    CREATE TABLE IF NOT EXISTS interruptions (
+        discussion INTEGER NOT NULL REFERENCES discussions (key),
+        number INTEGER NOT NULL,
+        raised_at TEXT NOT NULL,
+        resolved_at TEXT,
+        during_speech INTEGER,
+        PRIMARY KEY (discussion, number)
+    );
+    CREATE TABLE IF NOT EXISTS motions (
+        discussion INTEGER NOT NULL REFERENCES discussions (key),
+        number INTEGER NOT NULL,
+        text TEXT NOT NULL,
+        moved_by TEXT NOT NULL,
+        moved_at TEXT NOT NULL,
+        during_speech INTEGER,
+        outcome TEXT,
+        resolved_at TEXT,
+        PRIMARY KEY (discussion, number)
+    );
+";
+
+/// Keeps the history in an SQLite database, one table for each kind of row.
+///  Times are stored as RFC 3339 text in UTC, so they sort in order and can be
+///  read by anyone querying the database directly.
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(error: rusqlite::Error) -> Self {
+        return StoreError(error.to_string());
+    }
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    return time.to_rfc3339_opts(SecondsFormat::Millis, true);
+}
+
+// Times outside of years 0 to 9999 don't sort as text, so the ends of a period
+//  are brought within them first
+fn format_bound(time: &DateTime<Utc>) -> String {
+    let earliest: DateTime<Utc> = Utc.with_ymd_and_hms(0, 1, 1, 0, 0, 0).unwrap();
+    let latest: DateTime<Utc> = Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap();
+    return format_time(&(*time).clamp(earliest, latest));
+}
+
+fn parse_time(time: String) -> Result<DateTime<Utc>, StoreError> {
+    match DateTime::parse_from_rfc3339(&time) {
+        Ok(time) => return Ok(time.with_timezone(&Utc)),
+        Err(_) => return Err(StoreError(format!("\"{}\" is not a time", time))),
+    }
+}
+
+fn parse_outcome(outcome: String) -> Result<MotionOutcome, StoreError> {
+    match outcome.as_str() {
+        "Carried" => return Ok(MotionOutcome::Carried),
+        "Defeated" => return Ok(MotionOutcome::Defeated),
+        "Withdrawn" => return Ok(MotionOutcome::Withdrawn),
+        _ => return Err(StoreError(format!("\"{}\" is not what becomes of a motion", outcome))),
+    }
+}
+
+impl SqliteStore {
+
+    /// Opens the database at `path`, creating it (and its tables) if need be
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        return Self::with_connection(Connection::open(path)?);
+    }
+
+    pub fn in_memory() -> Result<Self, StoreError> {
+        return Self::with_connection(Connection::open_in_memory()?);
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, StoreError> {
+        connection.execute_batch(SCHEMA)?;
+        return Ok(SqliteStore { connection: connection });
+    }
+
+}
+
+impl Store for SqliteStore {
+
+    fn open_discussion(&mut self, row: &DiscussionRow) -> Result<DiscussionKey, StoreError> {
+        self.connection.execute(
+            "INSERT INTO discussions (id, opened_at, lifecycle, priority_mode, paused, duration) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![row.id, format_time(&row.opened_at), format!("{:?}", row.lifecycle), format!("{:?}", row.priority_mode), row.paused, row.duration],
+        )?;
+        return Ok(self.connection.last_insert_rowid());
+    }
+
+    fn update_discussion(&mut self, key: DiscussionKey, row: &DiscussionRow) -> Result<(), StoreError> {
+        let updated: usize = self.connection.execute(
+            "UPDATE discussions SET id = ?2, opened_at = ?3, lifecycle = ?4, priority_mode = ?5, paused = ?6, duration = ?7 WHERE key = ?1",
+            params![key, row.id, format_time(&row.opened_at), format!("{:?}", row.lifecycle), format!("{:?}", row.priority_mode), row.paused, row.duration],
+        )?;
+        if updated == 0 {
+            return Err(StoreError(format!("there is no discussion {}", key)));
+        }
+        return Ok(());
+    }
+
+    fn put_speaker(&mut self, key: DiscussionKey, row: &SpeakerRow) -> Result<(), StoreError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("INSERT OR IGNORE INTO speakers (discussion, name) VALUES (?1, ?2)", params![key, row.name])?;
+        transaction.execute("DELETE FROM aliases WHERE discussion = ?1 AND speaker = ?2", params![key, row.name])?;
+        // An alias can move from one speaker to another when they're merged
+        for alias in &row.aliases {
+            transaction.execute("INSERT OR REPLACE INTO aliases (discussion, alias, speaker) VALUES (?1, ?2, ?3)", params![key, alias, row.name])?;
+        }
+        transaction.commit()?;
+        return Ok(());
+    }
+
+    fn remove_speaker(&mut self, key: DiscussionKey, name: &str) -> Result<(), StoreError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM aliases WHERE discussion = ?1 AND speaker = ?2", params![key, name])?;
+        transaction.execute("DELETE FROM speakers WHERE discussion = ?1 AND name = ?2", params![key, name])?;
+        transaction.commit()?;
+        return Ok(());
+    }
+
+    fn put_speech(&mut self, key: DiscussionKey, row: &SpeechRow) -> Result<(), StoreError> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO speeches (discussion, id, speaker, is_response, queued_at, started_at, duration, position) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![key, row.id, row.speaker, row.is_response, format_time(&row.queued_at), row.started_at.as_ref().map(format_time), row.duration, format!("{:?}", row.position)],
+        )?;
+        return Ok(());
+    }
+
+    fn remove_speech(&mut self, key: DiscussionKey, id: usize) -> Result<(), StoreError> {
+        self.connection.execute("DELETE FROM speeches WHERE discussion = ?1 AND id = ?2", params![key, id])?;
+        return Ok(());
+    }
+
+    fn put_interruption(&mut self, key: DiscussionKey, row: &InterruptionRow) -> Result<(), StoreError> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO interruptions (discussion, number, raised_at, resolved_at, during_speech) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![key, row.number, format_time(&row.raised_at), row.resolved_at.as_ref().map(format_time), row.during_speech],
+        )?;
+        return Ok(());
+    }
+
+    fn put_motion(&mut self, key: DiscussionKey, row: &MotionRow) -> Result<(), StoreError> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO motions (discussion, number, text, moved_by, moved_at, during_speech, outcome, resolved_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![key, row.number, row.text, row.moved_by, format_time(&row.moved_at), row.during_speech, row.outcome.map(|outcome| format!("{:?}", outcome)), row.resolved_at.as_ref().map(format_time)],
+        )?;
+        return Ok(());
+    }
+
+    fn remove_motion(&mut self, key: DiscussionKey, number: usize) -> Result<(), StoreError> {
+        self.connection.execute("DELETE FROM motions WHERE discussion = ?1 AND number = ?2", params![key, number])?;
+        return Ok(());
+    }
+
+    fn speaker_totals(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<SpeakerTotal>, StoreError> {
+        let mut statement = self.connection.prepare("
+            SELECT speeches.speaker, COUNT(DISTINCT speeches.discussion), COUNT(*), SUM(speeches.duration)
+            FROM speeches JOIN discussions ON discussions.key = speeches.discussion
+            WHERE discussions.opened_at >= ?1 AND discussions.opened_at < ?2 AND speeches.position != 'Upcoming'
+            GROUP BY speeches.speaker
+            ORDER BY SUM(speeches.duration) DESC, speeches.speaker
+        ")?;
+        let totals = statement.query_map(params![format_bound(&from), format_bound(&until)], |row| Ok(SpeakerTotal {
+            name: row.get(0)?,
+            discussions: row.get(1)?,
+            speeches: row.get(2)?,
+            speaking_time: row.get(3)?,
+        }))?;
+        return Ok(totals.collect::<Result<Vec<SpeakerTotal>, rusqlite::Error>>()?);
+    }
+
+    fn response_times(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ResponseTimes>, StoreError> {
+        let mut statement = self.connection.prepare("
+            SELECT discussions.id, discussions.opened_at, COUNT(*), AVG(speeches.duration),
+                AVG((julianday(speeches.started_at) - julianday(speeches.queued_at)) * 86400.0)
+            FROM speeches JOIN discussions ON discussions.key = speeches.discussion
+            WHERE discussions.opened_at >= ?1 AND discussions.opened_at < ?2 AND speeches.position != 'Upcoming' AND speeches.is_response
+            GROUP BY discussions.key
+            ORDER BY discussions.opened_at, discussions.key
+        ")?;
+        let mut rows = statement.query(params![format_bound(&from), format_bound(&until)])?;
+        let mut times: Vec<ResponseTimes> = Vec::new();
+        while let Some(row) = rows.next()? {
+            times.push(ResponseTimes {
+                discussion: row.get(0)?,
+                opened_at: parse_time(row.get(1)?)?,
+                responses: row.get(2)?,
+                average_length: row.get(3)?,
+                average_wait: row.get(4)?,
+            });
+        }
+        return Ok(times);
+    }
+
+    fn motions(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<MovedMotion>, StoreError> {
+        let mut statement = self.connection.prepare("
+            SELECT discussions.id, discussions.opened_at, motions.number, motions.text, motions.moved_by, motions.moved_at,
+                motions.during_speech, motions.outcome, motions.resolved_at
+            FROM motions JOIN discussions ON discussions.key = motions.discussion
+            WHERE discussions.opened_at >= ?1 AND discussions.opened_at < ?2
+            ORDER BY motions.moved_at, discussions.key, motions.number
+        ")?;
+        let mut rows = statement.query(params![format_bound(&from), format_bound(&until)])?;
+        let mut motions: Vec<MovedMotion> = Vec::new();
+        while let Some(row) = rows.next()? {
+            motions.push(MovedMotion {
+                discussion: row.get(0)?,
+                opened_at: parse_time(row.get(1)?)?,
+                motion: MotionRow {
+                    number: row.get(2)?,
+                    text: row.get(3)?,
+                    moved_by: row.get(4)?,
+                    moved_at: parse_time(row.get(5)?)?,
+                    during_speech: row.get(6)?,
+                    outcome: row.get::<_, Option<String>>(7)?.map(parse_outcome).transpose()?,
+                    resolved_at: row.get::<_, Option<String>>(8)?.map(parse_time).transpose()?,
+                },
+            });
+        }
+        return Ok(motions);
+    }
+
+}
+
+#[test]
+fn test_sqlite_store() {
+    check_store(Box::new(SqliteStore::in_memory().unwrap()));
+}